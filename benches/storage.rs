@@ -0,0 +1,111 @@
+//! Compares the storage backends on a database of 100k records:
+//! `cargo bench --bench storage`
+
+use std::path::Path;
+
+use assert_fs::{prelude::PathChild, TempDir};
+use chrono::{TimeDelta, Utc};
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use time_tracker_rust::feature::tracker::{
+    FlatFileTracker, JsonlTracker, RecordId, SqliteTracker, Task, TimeRecord, Tracker,
+};
+
+const RECORDS: i64 = 100_000;
+
+fn records() -> Vec<TimeRecord> {
+    let first = Utc::now() - TimeDelta::hours(RECORDS + 1);
+
+    (0..RECORDS)
+        .map(|hour| {
+            let start = first + TimeDelta::hours(hour);
+            TimeRecord {
+                id: RecordId::new(),
+                start: start.into(),
+                end: (start + TimeDelta::minutes(30)).into(),
+                pauses: Vec::new(),
+                task: Task {
+                    project: Some(format!("project {}", hour % 10)),
+                    description: Some("benchmark".to_string()),
+                    tags: Default::default(),
+                },
+            }
+        })
+        .collect()
+}
+
+fn flatfile(dir: &Path) -> FlatFileTracker {
+    FlatFileTracker::new(dir.join("db.json"), dir.join("lockfile"))
+}
+
+fn jsonl(dir: &Path) -> JsonlTracker {
+    JsonlTracker::new(dir.join("records.jsonl"), dir.join("lockfile"))
+}
+
+fn sqlite(dir: &Path) -> SqliteTracker {
+    SqliteTracker::new(dir.join("db.sqlite"))
+}
+
+/// Copies the files of the seeded database, so that every iteration of a
+/// benchmark that changes the database starts from the same one.
+fn copy_database(seed: &Path) -> TempDir {
+    let dir = TempDir::new().unwrap();
+    for entry in std::fs::read_dir(seed).unwrap() {
+        let entry = entry.unwrap();
+        std::fs::copy(entry.path(), dir.join(entry.file_name())).unwrap();
+    }
+    dir
+}
+
+fn bench_backend<T, F>(c: &mut Criterion, name: &str, new_tracker: F)
+where
+    T: Tracker,
+    F: Fn(&Path) -> T,
+{
+    let temp = TempDir::new().unwrap();
+    let dir = temp.child(name);
+    std::fs::create_dir(&dir).unwrap();
+
+    let mut tracker = new_tracker(&dir);
+    tracker.import(records()).unwrap();
+
+    let mut group = c.benchmark_group(name);
+    group.sample_size(10);
+
+    group.bench_function("start and stop", |b| {
+        b.iter_batched(
+            || {
+                let dir = copy_database(&dir);
+                let tracker = new_tracker(&dir);
+                (dir, tracker)
+            },
+            |(_dir, mut tracker)| {
+                tracker.start(Task::default()).unwrap();
+                tracker.stop().unwrap();
+            },
+            BatchSize::PerIteration,
+        )
+    });
+    group.bench_function("records", |b| b.iter(|| tracker.records().unwrap().count()));
+    group.bench_function("import", |b| {
+        b.iter_batched(
+            || {
+                let dir = TempDir::new().unwrap();
+                let tracker = new_tracker(&dir);
+                (dir, tracker, records())
+            },
+            |(_dir, mut tracker, records)| tracker.import(records).unwrap(),
+            BatchSize::PerIteration,
+        )
+    });
+
+    group.finish();
+}
+
+fn storage(c: &mut Criterion) {
+    bench_backend(c, "flatfile", flatfile);
+    bench_backend(c, "jsonl", jsonl);
+    bench_backend(c, "sqlite", sqlite);
+}
+
+criterion_group!(benches, storage);
+criterion_main!(benches);
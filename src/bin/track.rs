@@ -1,13 +1,15 @@
+use std::process::ExitCode;
+
 use error_stack::{Result, ResultExt};
 use time_tracker_rust::{error::{AppError}, feature, init};
 
-fn main() -> Result<(), AppError> {
+fn main() -> Result<ExitCode, AppError> {
   init::error_reporting();
   init::tracing();
 
-  feature::cli::run().change_context(AppError).attach_printable("Failed to run CLI")?;
+  let code = feature::cli::run().change_context(AppError).attach_printable("Failed to run CLI")?;
 
   // return Err(Report::from(AppError)).attach(Suggestion("Try running the program again"));
 
-  Ok(())
+  Ok(code)
 }
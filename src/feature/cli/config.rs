@@ -0,0 +1,84 @@
+use std::path::{Path, PathBuf};
+
+use error_stack::{Result, ResultExt};
+use serde::Deserialize;
+
+use crate::error::Suggestion;
+
+use super::CLIError;
+
+/// Settings read from the config file, e.g.
+///
+/// ```toml
+/// backend = "sqlite"
+/// ```
+///
+/// Command line flags and environment variables take precedence.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct Config {
+    /// Storage backend, e.g. "sqlite"
+    pub backend: Option<String>,
+}
+
+impl Config {
+    /// Reads the config file at `path`, or at the default location when no
+    /// path is given. A missing default config file is an empty config.
+    pub fn load(path: Option<&Path>) -> Result<Config, CLIError> {
+        let (path, required) = match path {
+            Some(path) => (path.to_path_buf(), true),
+            None => match default_path() {
+                Some(path) => (path, false),
+                None => return Ok(Config::default()),
+            },
+        };
+
+        let data = match std::fs::read_to_string(&path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound && !required => return Ok(Config::default()),
+            Err(e) => {
+                return Err(e)
+                    .change_context(CLIError)
+                    .attach_printable(format!("unable to read config file {}", path.display()))
+            }
+        };
+
+        toml::from_str(&data)
+            .change_context(CLIError)
+            .attach_printable(format!("unable to parse config file {}", path.display()))
+            .attach(Suggestion("the config file holds settings such as `backend = \"sqlite\"`"))
+    }
+}
+
+/// "track/config.toml" in the config directory, e.g. `~/.config` on Linux.
+pub fn default_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("track").join("config.toml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_fs::{prelude::PathChild, TempDir};
+
+    use super::*;
+
+    #[test]
+    fn reads_backend_from_config_file() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.child("config.toml");
+        std::fs::write(&path, "backend = \"sqlite\"\n").unwrap();
+
+        let config = Config::load(Some(&path)).unwrap();
+
+        assert_eq!(config.backend.as_deref(), Some("sqlite"));
+    }
+
+    #[test]
+    fn missing_or_invalid_config_file_given_explicitly_is_an_error() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.child("config.toml");
+
+        assert!(Config::load(Some(&path)).is_err());
+
+        std::fs::write(&path, "backend = sqlite").unwrap();
+        assert!(Config::load(Some(&path)).is_err());
+    }
+}
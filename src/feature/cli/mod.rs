@@ -1,32 +1,419 @@
-use std::{path::PathBuf, time::Duration};
+pub mod config;
+pub mod time_expr;
 
-use clap::{Parser, Subcommand};
+use std::{
+    io::{IsTerminal, Write},
+    path::{Path, PathBuf},
+    process::{ExitCode, Stdio},
+    time::Duration,
+};
+
+use chrono::{DateTime, NaiveDate, TimeDelta, Utc, Weekday};
+use chrono_tz::Tz;
+use clap::{ArgGroup, Args, Parser, Subcommand, ValueEnum};
 use error_stack::{Result, ResultExt};
-use crate::feature::report_fmt::DurationFormatter;
+use crate::feature::report_fmt::{breakdown_table, csv, history_table, json, log_table, DurationFormatter};
 use crate::{
     error::Suggestion,
-    feature::{report_fmt::HMSFormatter, tracker::{FlatFileTracker, ReportTimespan, Reporter}},
+    feature::{
+        report_fmt::HMSFormatter,
+        tracker::{GroupBy, MigrationReport, ReportConfig, ReportTimespan, Reporter},
+    },
 };
 
 use super::tracker::StartupStatus;
-use crate::feature::tracker::Tracker;
+use crate::feature::tracker::{
+    convert, list_entries, AnyTracker, BackendKind, BackendSpec, Change, EndTime, EntryFilter, Journaled, RecordEdit, RecordId, Session, SortBy, StartTime, Task,
+    TimeRecord, Tracker,
+};
+use config::Config;
+use time_expr::{parse_duration, TimeExpr};
 
 #[derive(Debug, thiserror::Error)]
 #[error("CLI error occurred")]
 pub struct CLIError;
 
-#[derive(Debug, Clone, Copy, Subcommand)]
+#[derive(Debug, Clone, Subcommand)]
 pub enum Command {
-    /// Start tracking time
-    Start,
-    Stop,
-    Report,
+    /// Start tracking time, e.g. `track start --ago 15m`
+    Start {
+        #[command(flatten)]
+        at: InstantArgs,
+
+        #[command(flatten)]
+        task: TaskArgs,
+    },
+    /// Stop tracking time, e.g. `track stop --at 17:45`
+    Stop {
+        #[command(flatten)]
+        at: InstantArgs,
+    },
+    /// Stop the running session and start another in one step, e.g. `track switch --project blog`
+    Switch {
+        #[command(flatten)]
+        at: InstantArgs,
+
+        #[command(flatten)]
+        task: TaskArgs,
+    },
+    /// Pause the running session, e.g. `track pause --ago 5m`
+    Pause {
+        #[command(flatten)]
+        at: InstantArgs,
+    },
+    /// Resume the paused session, e.g. `track resume --at 13:00`
+    Resume {
+        #[command(flatten)]
+        at: InstantArgs,
+    },
+    /// Stop or discard a session left over from a crash or another machine, e.g. `track recover --at "yesterday 17:45"`
+    Recover {
+        #[command(flatten)]
+        at: InstantArgs,
+
+        /// Throw the session away instead of recording it
+        #[arg(long, conflicts_with_all = ["at", "ago"])]
+        discard: bool,
+    },
+    /// Discard the running session without recording it
+    Cancel {
+        /// Do not ask for confirmation, even for long sessions
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Record a session that was not tracked, e.g. `track add --from 09:00 --to 10:30`
+    Add {
+        /// When the session started, e.g. "09:00" or "2026-10-15 09:00"
+        #[arg(long, value_name = "TIME")]
+        from: TimeExpr,
+
+        /// When the session ended, e.g. "10:30" or "2026-10-15 10:30"
+        #[arg(long, value_name = "TIME")]
+        to: TimeExpr,
+
+        /// Day of times given without a date (YYYY-MM-DD), defaults to today
+        #[arg(long)]
+        date: Option<NaiveDate>,
+
+        #[command(flatten)]
+        task: TaskArgs,
+    },
+    /// Change a recorded session, e.g. `track edit 1a2b3c4d --end 17:45 --project website`
+    Edit(EditArgs),
+    /// Delete a recorded session
+    Delete {
+        /// ID of the record, or its first characters
+        id: String,
+    },
+    /// Revert the most recent change made by start, stop, switch, pause, resume, cancel, add, edit or delete
+    Undo,
+    /// Apply the most recently undone change again
+    Redo,
+    /// List the changes that can be undone and redone, newest first
+    History,
+    /// Show whether the tracker is running. Exits with 0 when running, 2 when stopped and 3 when paused
+    Status,
+    /// Report tracked time, by default for the last 24 hours
+    Report {
+        #[command(flatten)]
+        timespan: TimespanArgs,
+
+        /// Leave out the running session
+        #[arg(long)]
+        exclude_running: bool,
+
+        /// Break the report down by day, week, month, project or tag, e.g. "project,day"
+        #[arg(long, value_delimiter = ',')]
+        group_by: Vec<GroupBy>,
+    },
+    /// List recorded sessions, by default of the last 24 hours, e.g. `track log --week --project website`
+    Log(LogArgs),
+    /// Manage the database
+    #[command(subcommand)]
+    Db(DbCommand),
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum DbCommand {
+    /// Upgrade the database to the format of this version of track, after backing it up
+    Migrate {
+        /// Only show what would be done
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Copy all records and the running session to another backend, e.g. `track db convert --from flatfile:records.json --to sqlite:records.db`
+    Convert {
+        /// Backend to copy from, as KIND:PATH with KIND one of flatfile, jsonl, sqlite
+        #[arg(long, value_name = "KIND:PATH")]
+        from: BackendSpec,
+
+        /// Backend to copy to, as KIND:PATH
+        #[arg(long, value_name = "KIND:PATH")]
+        to: BackendSpec,
+
+        /// Replace the records and session the target already holds
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+/// Which time to report on. Only one timespan can be selected.
+#[derive(Debug, Clone, Default, Args)]
+#[group(skip)]
+#[command(group(
+    ArgGroup::new("timespan").args(["today", "yesterday", "week", "last_week", "month", "from"])
+))]
+pub struct TimespanArgs {
+    /// Report on today
+    #[arg(long)]
+    pub today: bool,
+
+    /// Report on yesterday
+    #[arg(long)]
+    pub yesterday: bool,
+
+    /// Report on the current week
+    #[arg(long)]
+    pub week: bool,
+
+    /// Report on the previous week
+    #[arg(long)]
+    pub last_week: bool,
+
+    /// Report on the current month
+    #[arg(long)]
+    pub month: bool,
+
+    /// Report from the start of this day (YYYY-MM-DD)
+    #[arg(long, value_name = "DATE")]
+    pub from: Option<NaiveDate>,
+
+    /// Report up to the end of this day (YYYY-MM-DD), requires --from
+    #[arg(long, value_name = "DATE", requires = "from")]
+    pub to: Option<NaiveDate>,
+}
+
+impl TimespanArgs {
+    pub fn timespan(&self) -> Result<ReportTimespan, CLIError> {
+        const TWENTY_FOUR_HOURS: u64 = 24 * 60 * 60;
+
+        let timespan = match (self.from, self.to) {
+            (Some(from), Some(to)) if to < from => {
+                return Err(CLIError)
+                    .attach_printable(format!("--to {to} is before --from {from}"))
+                    .attach(Suggestion("swap the --from and --to dates"));
+            }
+            (Some(from), Some(to)) => ReportTimespan::Between(from, to),
+            (Some(from), None) => ReportTimespan::Since(from),
+            _ if self.today => ReportTimespan::Today,
+            _ if self.yesterday => ReportTimespan::Yesterday,
+            _ if self.week => ReportTimespan::ThisWeek,
+            _ if self.last_week => ReportTimespan::LastWeek,
+            _ if self.month => ReportTimespan::ThisMonth,
+            _ => ReportTimespan::Last(Duration::from_secs(TWENTY_FOUR_HOURS)),
+        };
+
+        Ok(timespan)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Human readable text
+    Table,
+    /// JSON document, see the README for the schema
+    Json,
+    /// Comma separated values with a header line
+    Csv,
+}
+
+/// When the tracker is started or stopped, defaults to now
+#[derive(Debug, Clone, Default, Args)]
+#[group(multiple = false)]
+pub struct InstantArgs {
+    /// Local time, e.g. "17:45", "yesterday 17:45", "2026-10-15 17:45" or "15m ago"
+    #[arg(long, value_name = "TIME")]
+    pub at: Option<TimeExpr>,
+
+    /// How long ago, e.g. "15m" or "1h30m"
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub ago: Option<TimeDelta>,
+}
+
+impl InstantArgs {
+    /// The instant given on the command line. Fails for instants in the future.
+    pub fn instant(&self, now: DateTime<Utc>, tz: Tz) -> Result<DateTime<Utc>, CLIError> {
+        let instant = match (&self.at, self.ago) {
+            (Some(at), _) => resolve_time(at, today(now, tz), now, tz)?,
+            (None, Some(ago)) => now - ago,
+            (None, None) => now,
+        };
+
+        if instant > now {
+            return Err(CLIError)
+                .attach_printable(format!("{instant} is in the future"))
+                .attach(Suggestion("use \"yesterday HH:MM\" for times on the previous day"));
+        }
+
+        Ok(instant)
+    }
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct LogArgs {
+    #[command(flatten)]
+    pub timespan: TimespanArgs,
+
+    /// Leave out the running session
+    #[arg(long)]
+    pub exclude_running: bool,
+
+    /// Only list records of this project
+    #[arg(short, long)]
+    pub project: Option<String>,
+
+    /// Only list records with this tag, can be given multiple times
+    #[arg(short, long = "tag")]
+    pub tags: Vec<String>,
+
+    /// Only list records whose description contains this text, ignoring case
+    #[arg(short, long, value_name = "TEXT")]
+    pub search: Option<String>,
+
+    /// Sort by start, end, duration or project
+    #[arg(long, default_value_t = SortBy::Start)]
+    pub sort: SortBy,
+
+    /// List in reverse order, e.g. newest first
+    #[arg(short, long)]
+    pub reverse: bool,
+
+    /// List at most this many records
+    #[arg(short = 'n', long)]
+    pub limit: Option<usize>,
+
+    /// Print the listing directly instead of through $PAGER
+    #[arg(long)]
+    pub no_pager: bool,
+}
+
+impl LogArgs {
+    pub fn filter(&self) -> EntryFilter {
+        EntryFilter {
+            project: self.project.clone(),
+            tags: self.tags.clone(),
+            text: self.search.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct EditArgs {
+    /// ID of the record, or its first characters
+    pub id: String,
+
+    /// New start, times of day are on the day the record starts
+    #[arg(long, value_name = "TIME")]
+    pub start: Option<TimeExpr>,
+
+    /// New end, times of day are on the day the record ends
+    #[arg(long, value_name = "TIME")]
+    pub end: Option<TimeExpr>,
+
+    /// New project
+    #[arg(short, long, conflicts_with = "no_project")]
+    pub project: Option<String>,
+
+    /// Remove the project
+    #[arg(long)]
+    pub no_project: bool,
+
+    /// New description
+    #[arg(long, conflicts_with = "no_description")]
+    pub description: Option<String>,
+
+    /// Remove the description
+    #[arg(long)]
+    pub no_description: bool,
+
+    /// Tag to add, can be given multiple times
+    #[arg(short, long = "tag")]
+    pub tags: Vec<String>,
+
+    /// Tag to remove, can be given multiple times
+    #[arg(long = "untag", value_name = "TAG")]
+    pub untags: Vec<String>,
+}
+
+impl EditArgs {
+    /// The changes to `record`. Fails if nothing would change.
+    pub fn edit(&self, record: &TimeRecord, now: DateTime<Utc>, tz: Tz) -> Result<RecordEdit, CLIError> {
+        let local_date = |instant: DateTime<Utc>| instant.with_timezone(&tz).date_naive();
+
+        let edit = RecordEdit {
+            start: self
+                .start
+                .map(|start| resolve_time(&start, local_date(record.start.instant()), now, tz))
+                .transpose()?
+                .map(StartTime::from),
+            end: self
+                .end
+                .map(|end| resolve_time(&end, local_date(record.end.instant()), now, tz))
+                .transpose()?
+                .map(EndTime::from),
+            project: optional_change(&self.project, self.no_project),
+            description: optional_change(&self.description, self.no_description),
+            add_tags: self.tags.iter().cloned().collect(),
+            remove_tags: self.untags.iter().cloned().collect(),
+        };
+
+        if edit == RecordEdit::default() {
+            return Err(CLIError)
+                .attach_printable("nothing to change")
+                .attach(Suggestion("see `track edit --help` for what can be changed"));
+        }
+
+        Ok(edit)
+    }
+}
+
+fn optional_change(value: &Option<String>, remove: bool) -> Option<Option<String>> {
+    if remove {
+        Some(None)
+    } else {
+        value.clone().map(Some)
+    }
+}
+
+/// What the tracked time is spent on
+#[derive(Debug, Clone, Default, Args)]
+pub struct TaskArgs {
+    /// Project the time is spent on
+    #[arg(short, long)]
+    pub project: Option<String>,
+
+    /// Tag for the record, can be given multiple times
+    #[arg(short, long = "tag")]
+    pub tags: Vec<String>,
+
+    /// Description of the work
+    pub description: Option<String>,
+}
+
+impl From<TaskArgs> for Task {
+    fn from(args: TaskArgs) -> Self {
+        Task {
+            project: args.project,
+            description: args.description,
+            tags: args.tags.into_iter().collect(),
+        }
+    }
 }
 
 #[derive(Parser, Debug)]
 #[command(version, about, arg_required_else_help(true))]
 pub struct Cli {
-    /// Path to the database directory
+    /// Path to the database (defaults to a file in the data directory named after the backend)
     #[arg(short = 'd', long)]
     pub db_dir: Option<PathBuf>,
 
@@ -34,68 +421,657 @@ pub struct Cli {
     #[arg(short = 'l', long)]
     pub lockfile: Option<PathBuf>,
 
+    /// First day of the week in reports
+    #[arg(long, env = "TRACK_WEEK_START", default_value = "monday", global = true)]
+    pub week_start: Weekday,
+
+    /// Output format of reports and listings
+    #[arg(long, value_enum, default_value_t = OutputFormat::Table, global = true)]
+    pub format: OutputFormat,
+
+    /// Timezone used for calendar days, e.g. "Europe/Berlin" (defaults to the system timezone)
+    #[arg(long, env = "TRACK_TZ", global = true)]
+    pub tz: Option<Tz>,
+
+    /// Storage backend: flatfile, jsonl, sqlite or memory (defaults to the config file, then flatfile)
+    #[arg(long, env = "TRACK_BACKEND", global = true)]
+    pub backend: Option<String>,
+
+    /// Path to the config file (defaults to track/config.toml in the config directory)
+    #[arg(long, env = "TRACK_CONFIG", global = true)]
+    pub config: Option<PathBuf>,
+
     #[command(subcommand)]
     pub command: Command,
 }
 
-pub fn run() -> Result<(), CLIError> {
+/// Cancelling a session that has been running for longer asks for confirmation.
+pub const CANCEL_CONFIRM_THRESHOLD: Duration = Duration::from_secs(5 * 60);
+
+/// Pager used by `track log` when $PAGER is not set.
+pub const DEFAULT_PAGER: &str = "less -FRX";
+
+/// Exit code of `track status` when the tracker is running.
+pub const EXIT_RUNNING: u8 = 0;
+/// Exit code of `track status` when the tracker is stopped. Errors exit with 1.
+pub const EXIT_STOPPED: u8 = 2;
+/// Exit code of `track status` when the running session is paused.
+pub const EXIT_PAUSED: u8 = 3;
+
+pub fn run() -> Result<ExitCode, CLIError> {
     let args = Cli::parse();
 
-    let db_dir = flatfile_db_dir(&args)?;
+    let backend = backend_spec(&args)?;
     let lockfile = lockfile_path(&args)?;
 
-    let mut tracker = FlatFileTracker::new(db_dir, lockfile);
+    // The records of the memory backend are gone after the command, and so
+    // is its journal
+    let scratch = (backend.kind == BackendKind::Memory).then(ScratchJournal::new);
+    let journal = match &scratch {
+        Some(scratch) => scratch.0.clone(),
+        None => journal_path(&backend.path),
+    };
+    let mut tracker = Journaled::new(backend.open(&lockfile), journal);
 
     match args.command {
-        Command::Start => match tracker.start() {
-            Ok(StartupStatus::Started) => println!("Tracker started"),
-            Ok(StartupStatus::Running) => println!("Tracker already running"),
-            Err(e) => return Err(e).change_context(CLIError),
+        Command::Start { at, task } => {
+            let start = at.instant(Utc::now(), timezone(args.tz))?;
+
+            match tracker.start_at(task.into(), start.into()) {
+                Ok(StartupStatus::Started) => println!("Tracker started"),
+                Ok(StartupStatus::Running) => {
+                    println!("Tracker already running");
+                    if let Ok(Some(session)) = tracker.session() {
+                        warn_if_stale(&session, Utc::now());
+                    }
+                }
+                Err(e) => return Err(e).change_context(CLIError),
+            }
+        }
+        Command::Stop { at } => {
+            let end = at.instant(Utc::now(), timezone(args.tz))?;
+
+            tracker
+                .stop_at(end.into())
+                .change_context(CLIError)
+                .attach_printable("failed to stop tracker")?;
+        }
+        Command::Switch { at, task } => {
+            let at = at.instant(Utc::now(), timezone(args.tz))?;
+
+            let record = tracker
+                .switch_at(task.into(), at.into())
+                .change_context(CLIError)
+                .attach_printable("failed to switch task")?;
+
+            match record {
+                Some(record) => println!("Record {} added, tracker started", record.id.short()),
+                None => println!("Tracker started"),
+            }
+        }
+        Command::Pause { at } => {
+            let at = at.instant(Utc::now(), timezone(args.tz))?;
+
+            tracker
+                .pause_at(at)
+                .change_context(CLIError)
+                .attach_printable("failed to pause tracker")?;
+
+            println!("Tracker paused");
+        }
+        Command::Resume { at } => {
+            let at = at.instant(Utc::now(), timezone(args.tz))?;
+
+            tracker
+                .resume_at(at)
+                .change_context(CLIError)
+                .attach_printable("failed to resume tracker")?;
+
+            println!("Tracker resumed");
+        }
+        Command::Recover { at, discard } => {
+            let session = tracker
+                .session()
+                .change_context(CLIError)
+                .attach_printable("failed to query running session")?
+                .ok_or(CLIError)
+                .attach_printable("tracker is not running, there is nothing to recover")?;
+
+            if discard {
+                tracker
+                    .cancel()
+                    .change_context(CLIError)
+                    .attach_printable("failed to discard session")?;
+
+                println!("Session discarded");
+            } else {
+                if at.at.is_none() && at.ago.is_none() {
+                    return Err(CLIError)
+                        .attach_printable("no recovery chosen")
+                        .attach(Suggestion("use --at or --ago to stop the session, or --discard to throw it away"));
+                }
+                let end = at.instant(Utc::now(), timezone(args.tz))?;
+
+                let record = tracker
+                    .stop_at(end.into())
+                    .change_context(CLIError)
+                    .attach_printable("failed to stop session")
+                    .attach_printable_lazy(|| format!("the session started at {}", session.start.instant()))?;
+
+                println!("Record {} added", record.id.short());
+            }
+        }
+        Command::Cancel { yes } => {
+            let session = tracker
+                .session()
+                .change_context(CLIError)
+                .attach_printable("failed to query running session")?
+                .ok_or(CLIError)
+                .attach_printable("tracker is not running")?;
+
+            let elapsed = session.tracked(Utc::now()).to_std().unwrap_or_default();
+            if !yes && elapsed > CANCEL_CONFIRM_THRESHOLD {
+                let question = format!(
+                    "Discard the session that has been running for {}?",
+                    HMSFormatter.format(elapsed)
+                );
+                if !confirm(&question)? {
+                    return Err(CLIError)
+                        .attach_printable("cancel was not confirmed")
+                        .attach(Suggestion("use --yes to cancel without confirmation"));
+                }
+            }
+
+            tracker
+                .cancel()
+                .change_context(CLIError)
+                .attach_printable("failed to cancel tracker")?;
+
+            println!("Tracker cancelled");
+        }
+        Command::Add {
+            from,
+            to,
+            date,
+            task,
+        } => {
+            let tz = timezone(args.tz);
+            let now = Utc::now();
+            let date = date.unwrap_or_else(|| today(now, tz));
+            let record = TimeRecord {
+                id: RecordId::new(),
+                start: resolve_time(&from, date, now, tz)?.into(),
+                end: resolve_time(&to, date, now, tz)?.into(),
+                pauses: Vec::new(),
+                task: task.into(),
+            };
+
+            let id = record.id;
+            let overlaps = tracker
+                .add(record)
+                .change_context(CLIError)
+                .attach_printable("failed to add record")
+                .attach(Suggestion("--to must be later than --from"))?;
+
+            println!("Record {} added", id.short());
+            for overlap in overlaps {
+                eprintln!(
+                    "warning: overlaps with record {} from {} to {}",
+                    overlap.id.short(),
+                    overlap.start.instant().with_timezone(&tz).format("%Y-%m-%d %H:%M"),
+                    overlap.end.instant().with_timezone(&tz).format("%Y-%m-%d %H:%M"),
+                );
+            }
+        }
+        Command::Edit(edit) => {
+            let record = tracker
+                .find(&edit.id)
+                .change_context(CLIError)
+                .attach_printable("failed to find record")?;
+            let changes = edit.edit(&record, Utc::now(), timezone(args.tz))?;
+
+            tracker
+                .edit(record.id, &changes)
+                .change_context(CLIError)
+                .attach_printable("failed to edit record")?;
+
+            println!("Record {} updated", record.id.short());
+        }
+        Command::Delete { id } => {
+            let record = tracker
+                .find(&id)
+                .change_context(CLIError)
+                .attach_printable("failed to find record")?;
+
+            tracker
+                .delete(record.id)
+                .change_context(CLIError)
+                .attach_printable("failed to delete record")?;
+
+            println!("Record {} deleted", record.id.short());
+        }
+        Command::Undo => match tracker.undo().change_context(CLIError).attach_printable("failed to undo")? {
+            Some(entry) => println!("Undid {}", describe(&entry.change)),
+            None => println!("Nothing to undo"),
         },
-        Command::Stop => tracker
-            .stop()
-            .change_context(CLIError)
-            .attach_printable("failed to stop tracker")?,
-        Command::Report => {
-            const TWENTY_FOUR_HOURS: u64 = 24 * 60 * 60;
-            let duration = Duration::from_secs(TWENTY_FOUR_HOURS);
+        Command::Redo => match tracker.redo().change_context(CLIError).attach_printable("failed to redo")? {
+            Some(entry) => println!("Redid {}", describe(&entry.change)),
+            None => println!("Nothing to redo"),
+        },
+        Command::History => {
+            let history = tracker
+                .history()
+                .change_context(CLIError)
+                .attach_printable("failed to read history")?;
+
+            match args.format {
+                OutputFormat::Table if history.is_empty() => println!("No changes"),
+                OutputFormat::Table => print!("{}", history_table(&history, timezone(args.tz))),
+                OutputFormat::Json => println!("{}", json_output(json::history(&history))?),
+                OutputFormat::Csv => print!("{}", csv::history(&history)),
+            }
+        }
+        Command::Status => return status(&tracker, timezone(args.tz), args.format),
+        Command::Report {
+            timespan,
+            exclude_running,
+            group_by,
+        } => {
+            let config = ReportConfig {
+                week_start: args.week_start,
+                timezone: timezone(args.tz),
+                include_running: !exclude_running,
+            };
+
+            report(&tracker, timespan.timespan()?, &group_by, &config, args.format)?;
+        }
+        Command::Log(log_args) => {
+            let config = ReportConfig {
+                week_start: args.week_start,
+                timezone: timezone(args.tz),
+                include_running: !log_args.exclude_running,
+            };
+
+            log(&tracker, &log_args, &config, args.format)?;
+        }
+        Command::Db(DbCommand::Migrate { dry_run }) => {
+            let AnyTracker::FlatFile(flatfile) = tracker.inner_mut() else {
+                return Err(CLIError)
+                    .attach_printable(format!("the {} backend has no format versions to migrate", backend.kind))
+                    .attach(Suggestion("SQLite databases are migrated when they are opened, other backends need no migration"));
+            };
+            let report = flatfile
+                .migrate(dry_run)
+                .change_context(CLIError)
+                .attach_printable("failed to migrate database")?;
 
+            print_migration(&report, dry_run);
+        }
+        Command::Db(DbCommand::Convert { from, to, force }) => convert_backend(&from, &to, &lockfile, force)?,
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn convert_backend(from: &BackendSpec, to: &BackendSpec, lockfile: &Path, force: bool) -> Result<(), CLIError> {
+    if from.same_database(to) {
+        return Err(CLIError)
+            .attach_printable(format!("{from} and {to} are the same database"))
+            .attach(Suggestion("give --to the path of a new database"));
+    }
+
+    // File backends both keep the running session in the lockfile
+    let copy_session = !(from.kind.uses_lockfile() && to.kind.uses_lockfile());
+    let conversion = convert(&mut from.open(lockfile), &mut to.open(lockfile), copy_session, force)
+        .change_context(CLIError)
+        .attach_printable(format!("failed to convert {from} to {to}"))?;
+
+    println!(
+        "Copied {} records ({} tracked) from {from} to {to}",
+        conversion.records,
+        HMSFormatter.format(conversion.duration.to_std().unwrap_or_default())
+    );
+    if conversion.session {
+        println!("Copied the running session");
+    }
+
+    Ok(())
+}
+
+fn print_migration(report: &MigrationReport, dry_run: bool) {
+    if report.migrations.is_empty() {
+        println!("Database is up to date (format version {})", report.to);
+        return;
+    }
+
+    let verb = if dry_run { "Would migrate" } else { "Migrated" };
+    println!("{verb} database from format version {} to {}:", report.from, report.to);
+    for (migration, version) in report.migrations.iter().zip(report.from + 1..) {
+        println!("  {version}: {migration}");
+    }
+    if let Some(backup) = &report.backup {
+        let verb = if dry_run { "Would back up" } else { "Backed up" };
+        println!("{verb} the previous database to {}", backup.display());
+    }
+}
+
+fn status<T>(tracker: &T, tz: Tz, format: OutputFormat) -> Result<ExitCode, CLIError>
+where
+    T: Tracker,
+{
+    let session = tracker
+        .session()
+        .change_context(CLIError)
+        .attach_printable("failed to query running session")?;
+    let now = Utc::now();
+
+    match format {
+        OutputFormat::Table => match &session {
+            Some(session) => {
+                let elapsed = session.tracked(now).to_std().unwrap_or_default();
+                let since = session.start.instant().with_timezone(&tz);
+
+                println!(
+                    "Running for {} since {}",
+                    HMSFormatter.format(elapsed),
+                    since.format("%Y-%m-%d %H:%M:%S %Z")
+                );
+                if let Some(paused_at) = session.paused_at {
+                    println!("  paused since {}", paused_at.with_timezone(&tz).format("%Y-%m-%d %H:%M:%S %Z"));
+                }
+                if let Some(origin) = &session.origin {
+                    println!("  started on: {} (pid {})", origin.hostname, origin.pid);
+                }
+                if let Some(project) = &session.task.project {
+                    println!("  project: {project}");
+                }
+                if let Some(description) = &session.task.description {
+                    println!("  description: {description}");
+                }
+                if !session.task.tags.is_empty() {
+                    let tags = session.task.tags.iter().cloned().collect::<Vec<_>>();
+                    println!("  tags: {}", tags.join(", "));
+                }
+                warn_if_stale(session, now);
+            }
+            None => println!("Stopped"),
+        },
+        OutputFormat::Json => println!("{}", json_output(json::status(session.as_ref(), now))?),
+        OutputFormat::Csv => print!("{}", csv::status(session.as_ref(), now)),
+    }
+
+    match session {
+        Some(session) if session.is_paused() => Ok(ExitCode::from(EXIT_PAUSED)),
+        Some(_) => Ok(ExitCode::from(EXIT_RUNNING)),
+        None => Ok(ExitCode::from(EXIT_STOPPED)),
+    }
+}
+
+fn report<T>(
+    tracker: &T,
+    timespan: ReportTimespan,
+    group_by: &[GroupBy],
+    config: &ReportConfig,
+    format: OutputFormat,
+) -> Result<(), CLIError>
+where
+    T: Reporter,
+{
+    let formatter = HMSFormatter;
+    let running = config.include_running && tracker.is_running();
+
+    match (format, group_by.is_empty()) {
+        (OutputFormat::Table, true) => {
             let total_duration = tracker
-                .total_duration(ReportTimespan::Last(duration))
+                .total_duration(timespan, config)
                 .change_context(CLIError)
                 .attach_printable("failed to calculate total duration")?;
 
-              let formatter = HMSFormatter::default();
+            if running {
+                println!("{} (running)", formatter.format(total_duration));
+            } else {
+                println!("{}", formatter.format(total_duration));
+            }
+        }
+        (format, true) => {
+            let entries = tracker
+                .entries(timespan, config)
+                .change_context(CLIError)
+                .attach_printable("failed to query records")?;
+
+            if format == OutputFormat::Json {
+                let window = timespan.window(config, Utc::now());
+                println!("{}", json_output(json::records_report(&window, &entries))?);
+            } else {
+                print!("{}", csv::records(&entries));
+            }
+        }
+        (format, false) => {
+            let breakdown = tracker
+                .breakdown(timespan, group_by, config)
+                .change_context(CLIError)
+                .attach_printable("failed to calculate breakdown")?;
 
-              println!("{}", formatter.format(total_duration));
+            match format {
+                OutputFormat::Table => {
+                    print!("{}", breakdown_table(&breakdown, group_by, &formatter));
+                    if running {
+                        println!("(includes the running session)");
+                    }
+                }
+                OutputFormat::Json => {
+                    let window = timespan.window(config, Utc::now());
+                    let report = json::breakdown_report(&window, &breakdown, group_by, running);
+                    println!("{}", json_output(report)?);
+                }
+                OutputFormat::Csv => print!("{}", csv::breakdown(&breakdown, group_by)),
+            }
         }
     }
 
     Ok(())
 }
 
-fn flatfile_db_dir(args: &Cli) -> Result<PathBuf, CLIError> {
-    match &args.db_dir {
-        Some(db_dir) => Ok(db_dir.clone()),
-        None => {
-            let mut db_path: PathBuf = dirs::data_dir()
-                .ok_or(CLIError)
-                .attach_printable("failed to get data dir")
-                .attach(Suggestion("use -d flag to specify the database directory"))?;
+fn log<T>(tracker: &T, args: &LogArgs, config: &ReportConfig, format: OutputFormat) -> Result<(), CLIError>
+where
+    T: Reporter,
+{
+    let timespan = args.timespan.timespan()?;
+    let entries = tracker
+        .entries(timespan, config)
+        .change_context(CLIError)
+        .attach_printable("failed to query records")?;
+    let entries = list_entries(entries, &args.filter(), args.sort, args.reverse, args.limit);
 
-            db_path.push("track");
+    match format {
+        OutputFormat::Table if entries.is_empty() => println!("No records"),
+        OutputFormat::Table if args.no_pager => print!("{}", log_table(&entries, config.timezone, &HMSFormatter)),
+        OutputFormat::Table => page(&log_table(&entries, config.timezone, &HMSFormatter))?,
+        OutputFormat::Json => {
+            let window = timespan.window(config, Utc::now());
+            println!("{}", json_output(json::records_report(&window, &entries))?);
+        }
+        OutputFormat::Csv => print!("{}", csv::records(&entries)),
+    }
 
-            std::fs::create_dir_all(&db_path)
-                .change_context(CLIError)
-                .attach_printable("failed to create database directory")?;
+    Ok(())
+}
+
+/// Prints text through the pager in $PAGER, or [`DEFAULT_PAGER`], when
+/// stdout is a terminal. Falls back to printing directly if the pager can not
+/// be started.
+fn page(text: &str) -> Result<(), CLIError> {
+    let pager = std::env::var("PAGER").unwrap_or_else(|_| DEFAULT_PAGER.to_string());
+    let mut words = pager.split_whitespace();
+
+    let child = match words.next() {
+        Some(program) if std::io::stdout().is_terminal() => std::process::Command::new(program)
+            .args(words)
+            .stdin(Stdio::piped())
+            .spawn()
+            .ok(),
+        _ => None,
+    };
+    let Some(mut child) = child else {
+        print!("{text}");
+        return Ok(());
+    };
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // The pager closes its input when it is quit early
+        let _ = stdin.write_all(text.as_bytes());
+    }
+    child
+        .wait()
+        .change_context(CLIError)
+        .attach_printable("failed to wait for the pager")?;
+
+    Ok(())
+}
+
+/// Warns on stderr if the running session looks left over, e.g. from a crash.
+fn warn_if_stale(session: &Session, now: DateTime<Utc>) {
+    let reasons = session.stale_reasons(now);
+    for reason in &reasons {
+        eprintln!("warning: {reason}");
+    }
+    if !reasons.is_empty() {
+        eprintln!("use `track recover --at TIME` to stop the session or `track recover --discard` to throw it away");
+    }
+}
+
+/// Asks a yes/no question on stderr and reads the answer from stdin. Anything
+/// but "y" or "yes" is a no.
+fn confirm(question: &str) -> Result<bool, CLIError> {
+    eprint!("{question} [y/N] ");
+
+    let mut answer = String::new();
+    std::io::stdin()
+        .read_line(&mut answer)
+        .change_context(CLIError)
+        .attach_printable("failed to read answer")?;
+
+    Ok(matches!(answer.trim().to_ascii_lowercase().as_str(), "y" | "yes"))
+}
+
+/// Short description of a change, e.g. "stop of record 1a2b3c4d".
+fn describe(change: &Change) -> String {
+    match change.record() {
+        Some(record) => format!("{} of record {}", change.name(), record.id.short()),
+        None => change.name().to_string(),
+    }
+}
 
-            db_path.push("records.json");
+/// The instant `expr` refers to, with times of day on `date` in `tz`.
+fn resolve_time(
+    expr: &TimeExpr,
+    date: NaiveDate,
+    now: DateTime<Utc>,
+    tz: Tz,
+) -> Result<DateTime<Utc>, CLIError> {
+    expr.resolve(date, now, tz)
+        .ok_or(CLIError)
+        .attach_printable(format!("the time does not exist in {tz}, it is skipped by a clock change"))
+}
+
+fn today(now: DateTime<Utc>, tz: Tz) -> NaiveDate {
+    now.with_timezone(&tz).date_naive()
+}
+
+fn json_output(json: serde_json::Result<String>) -> Result<String, CLIError> {
+    json.change_context(CLIError)
+        .attach_printable("failed to serialize JSON output")
+}
+
+/// The timezone given on the command line, or the system timezone.
+fn timezone(tz: Option<Tz>) -> Tz {
+    if let Some(tz) = tz {
+        return tz;
+    }
 
-            Ok(db_path)
+    match iana_time_zone::get_timezone() {
+        Ok(name) => name.parse().unwrap_or_else(|_| {
+            tracing::warn!(timezone = name, "unknown system timezone, using UTC");
+            Tz::UTC
+        }),
+        Err(e) => {
+            tracing::warn!(error = %e, "unable to detect system timezone, using UTC");
+            Tz::UTC
         }
     }
 }
 
+/// The backend named by --backend or TRACK_BACKEND, else by the config file,
+/// else the flat file backend.
+fn backend_spec(args: &Cli) -> Result<BackendSpec, CLIError> {
+    let name = match &args.backend {
+        Some(name) => Some(name.clone()),
+        None => Config::load(args.config.as_deref())?.backend,
+    };
+    let kind = match name {
+        Some(name) => name
+            .parse::<BackendKind>()
+            .change_context(CLIError)
+            .attach(Suggestion("name the backend with --backend, TRACK_BACKEND or `backend` in the config file"))?,
+        None => BackendKind::FlatFile,
+    };
+
+    Ok(BackendSpec {
+        kind,
+        path: database_path(args, kind)?,
+    })
+}
+
+fn database_path(args: &Cli, kind: BackendKind) -> Result<PathBuf, CLIError> {
+    if let Some(db_dir) = &args.db_dir {
+        return Ok(db_dir.clone());
+    }
+    let Some(file_name) = kind.file_name() else {
+        return Ok(PathBuf::new());
+    };
+
+    let mut db_path: PathBuf = dirs::data_dir()
+        .ok_or(CLIError)
+        .attach_printable("failed to get data dir")
+        .attach(Suggestion("use -d flag to specify the database directory"))?;
+
+    db_path.push("track");
+
+    std::fs::create_dir_all(&db_path)
+        .change_context(CLIError)
+        .attach_printable("failed to create database directory")?;
+
+    db_path.push(file_name);
+
+    Ok(db_path)
+}
+
+/// The journal used by undo and redo, kept next to the database.
+fn journal_path(db: &Path) -> PathBuf {
+    let mut path = db.as_os_str().to_owned();
+    path.push(".journal");
+    PathBuf::from(path)
+}
+
+/// Journal in the temporary directory, removed with its lock when dropped.
+struct ScratchJournal(PathBuf);
+
+impl ScratchJournal {
+    fn new() -> Self {
+        ScratchJournal(std::env::temp_dir().join(format!("track-{}.journal", std::process::id())))
+    }
+}
+
+impl Drop for ScratchJournal {
+    fn drop(&mut self) {
+        let mut lock = self.0.as_os_str().to_owned();
+        lock.push(".lock");
+
+        let _ = std::fs::remove_file(&self.0);
+        let _ = std::fs::remove_file(lock);
+    }
+}
+
 fn lockfile_path(args: &Cli) -> Result<PathBuf, CLIError> {
     match &args.lockfile {
         Some(lockfile) => Ok(lockfile.clone()),
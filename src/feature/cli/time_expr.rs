@@ -0,0 +1,186 @@
+//! Points in time and durations given on the command line, see the README
+
+use std::str::FromStr;
+
+use chrono::{DateTime, Days, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, TimeZone, Utc};
+use chrono_tz::Tz;
+
+#[derive(Debug, thiserror::Error)]
+#[error("invalid time '{0}', expected e.g. \"17:45\", \"2026-10-15 17:45\", \"now\" or \"15m ago\"")]
+pub struct TimeExprError(String);
+
+#[derive(Debug, thiserror::Error)]
+#[error("invalid duration '{0}', expected e.g. \"15m\" or \"1h30m\"")]
+pub struct DurationError(String);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeExpr {
+    /// The moment the command runs
+    Now,
+    /// This long before the command runs
+    Ago(TimeDelta),
+    /// Time of day on the day the expression is resolved against
+    Time(NaiveTime),
+    /// Time of day on the day before
+    Yesterday(NaiveTime),
+    /// Local date and time
+    DateTime(NaiveDateTime),
+    /// Exact instant
+    Instant(DateTime<Utc>),
+}
+
+impl TimeExpr {
+    /// The instant the expression refers to. Times of day are on `date` in
+    /// `tz`, and ambiguous local times resolve to the earlier instant.
+    /// Returns `None` for local times that do not exist in `tz`.
+    pub fn resolve(&self, date: NaiveDate, now: DateTime<Utc>, tz: Tz) -> Option<DateTime<Utc>> {
+        let local = |datetime: NaiveDateTime| {
+            tz.from_local_datetime(&datetime)
+                .earliest()
+                .map(|instant| instant.with_timezone(&Utc))
+        };
+
+        match *self {
+            TimeExpr::Now => Some(now),
+            TimeExpr::Ago(delta) => Some(now - delta),
+            TimeExpr::Time(time) => local(date.and_time(time)),
+            TimeExpr::Yesterday(time) => local(date.checked_sub_days(Days::new(1))?.and_time(time)),
+            TimeExpr::DateTime(datetime) => local(datetime),
+            TimeExpr::Instant(instant) => Some(instant),
+        }
+    }
+}
+
+impl FromStr for TimeExpr {
+    type Err = TimeExprError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let error = || TimeExprError(s.to_string());
+        let s = s.trim();
+
+        if s.eq_ignore_ascii_case("now") {
+            return Ok(TimeExpr::Now);
+        }
+        if let Some(duration) = s.strip_suffix("ago") {
+            return parse_duration(duration.trim())
+                .map(TimeExpr::Ago)
+                .map_err(|_| error());
+        }
+        if let Some(time) = s.strip_prefix("yesterday") {
+            return parse_time(time.trim()).map(TimeExpr::Yesterday).ok_or_else(error);
+        }
+        if let Some(time) = parse_time(s) {
+            return Ok(TimeExpr::Time(time));
+        }
+        if let Ok(instant) = DateTime::parse_from_rfc3339(s) {
+            return Ok(TimeExpr::Instant(instant.with_timezone(&Utc)));
+        }
+
+        ["%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S"]
+            .iter()
+            .find_map(|format| NaiveDateTime::parse_from_str(s, format).ok())
+            .map(TimeExpr::DateTime)
+            .ok_or_else(error)
+    }
+}
+
+fn parse_time(s: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(s, "%H:%M")
+        .or_else(|_| NaiveTime::parse_from_str(s, "%H:%M:%S"))
+        .ok()
+}
+
+/// Parses a duration such as `15m`, `1h30m` or `2d`.
+pub fn parse_duration(s: &str) -> std::result::Result<TimeDelta, DurationError> {
+    let error = || DurationError(s.to_string());
+
+    let mut total = TimeDelta::zero();
+    let mut digits = String::new();
+
+    for c in s.trim().chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+
+        let amount = digits.parse::<i64>().map_err(|_| error())?;
+        let unit = match c {
+            'd' => TimeDelta::try_days(amount),
+            'h' => TimeDelta::try_hours(amount),
+            'm' => TimeDelta::try_minutes(amount),
+            's' => TimeDelta::try_seconds(amount),
+            _ => None,
+        }
+        .ok_or_else(error)?;
+
+        total = total.checked_add(&unit).ok_or_else(error)?;
+        digits.clear();
+    }
+
+    if !digits.is_empty() || total.is_zero() {
+        return Err(error());
+    }
+
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    fn resolve(expr: &str, tz: Tz) -> Option<DateTime<Utc>> {
+        let now = utc("2026-10-15T12:00:00Z");
+        let date = now.with_timezone(&tz).date_naive();
+        expr.parse::<TimeExpr>().unwrap().resolve(date, now, tz)
+    }
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("15m").unwrap(), TimeDelta::minutes(15));
+        assert_eq!(parse_duration("1h30m").unwrap(), TimeDelta::minutes(90));
+        assert_eq!(parse_duration("2d").unwrap(), TimeDelta::days(2));
+        assert_eq!(parse_duration("45s").unwrap(), TimeDelta::seconds(45));
+    }
+
+    #[test]
+    fn rejects_invalid_durations() {
+        for duration in ["", "15", "m", "0m", "15x", "1.5h", "-15m"] {
+            assert!(parse_duration(duration).is_err(), "{duration}");
+        }
+    }
+
+    #[test]
+    fn relative_expressions_count_back_from_now() {
+        assert_eq!(resolve("now", Tz::UTC), Some(utc("2026-10-15T12:00:00Z")));
+        assert_eq!(resolve("15m ago", Tz::UTC), Some(utc("2026-10-15T11:45:00Z")));
+        assert_eq!(resolve("1h30m ago", Tz::UTC), Some(utc("2026-10-15T10:30:00Z")));
+    }
+
+    #[test]
+    fn absolute_expressions_are_local_times() {
+        let tz = Tz::Europe__Berlin;
+
+        assert_eq!(resolve("17:45", tz), Some(utc("2026-10-15T15:45:00Z")));
+        assert_eq!(resolve("yesterday 17:45", tz), Some(utc("2026-10-14T15:45:00Z")));
+        assert_eq!(resolve("2026-10-13 08:00", tz), Some(utc("2026-10-13T06:00:00Z")));
+        assert_eq!(resolve("2026-10-13T08:00:30", tz), Some(utc("2026-10-13T06:00:30Z")));
+        assert_eq!(resolve("2026-10-13T08:00:00Z", tz), Some(utc("2026-10-13T08:00:00Z")));
+    }
+
+    #[test]
+    fn skipped_local_time_does_not_resolve() {
+        // Clocks in Berlin jump from 02:00 to 03:00 on 2026-03-29
+        assert_eq!(resolve("2026-03-29 02:30", Tz::Europe__Berlin), None);
+    }
+
+    #[test]
+    fn rejects_invalid_expressions() {
+        for expr in ["", "later", "25:00", "ago", "yesterday", "2026-10-15", "15m"] {
+            assert!(expr.parse::<TimeExpr>().is_err(), "{expr}");
+        }
+    }
+}
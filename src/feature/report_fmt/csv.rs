@@ -0,0 +1,209 @@
+//! CSV output, see the README
+
+use std::time::Duration;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+
+use crate::feature::tracker::{Breakdown, GroupBy, History, ReportEntry, Session, Task};
+
+/// One row per record: `start,end,duration_seconds,project,description,tags,id`.
+/// `end` and `id` are empty for the running session.
+pub fn records(entries: &[ReportEntry]) -> String {
+    let mut csv = String::from("start,end,duration_seconds,project,description,tags,id\n");
+
+    for entry in entries {
+        push_row(
+            &mut csv,
+            &[
+                &timestamp(entry.start.instant()),
+                &entry
+                    .end
+                    .map(|end| timestamp(end.instant()))
+                    .unwrap_or_default(),
+                &entry.duration.as_secs().to_string(),
+                entry.task.project.as_deref().unwrap_or_default(),
+                entry.task.description.as_deref().unwrap_or_default(),
+                &tags(&entry.task),
+                &entry.id.map(|id| id.to_string()).unwrap_or_default(),
+            ],
+        );
+    }
+
+    csv
+}
+
+/// One row per innermost group, with a column for each grouping followed by
+/// `duration_seconds`.
+pub fn breakdown(breakdown: &Breakdown, group_by: &[GroupBy]) -> String {
+    let mut header = group_by.iter().map(GroupBy::name).collect::<Vec<_>>();
+    header.push("duration_seconds");
+
+    let mut csv = String::new();
+    push_row(&mut csv, &header);
+    push_group_rows(&mut csv, breakdown, &mut Vec::new());
+
+    csv
+}
+
+/// A single row: `state,start,elapsed_seconds,project,description,tags`, where
+/// `state` is `running`, `paused` or `stopped`.
+pub fn status(session: Option<&Session>, now: DateTime<Utc>) -> String {
+    let mut csv = String::from("state,start,elapsed_seconds,project,description,tags\n");
+
+    match session {
+        Some(session) => {
+            let elapsed = session.tracked(now).num_seconds().max(0);
+            push_row(
+                &mut csv,
+                &[
+                    if session.is_paused() { "paused" } else { "running" },
+                    &timestamp(session.start.instant()),
+                    &elapsed.to_string(),
+                    session.task.project.as_deref().unwrap_or_default(),
+                    session.task.description.as_deref().unwrap_or_default(),
+                    &tags(&session.task),
+                ],
+            );
+        }
+        None => push_row(&mut csv, &["stopped", "", "", "", "", ""]),
+    }
+
+    csv
+}
+
+/// One row per change, newest first: `at,change,undone,record,start,end`.
+/// `record` and `end` are empty for changes to the running session.
+pub fn history(history: &History) -> String {
+    let mut csv = String::from("at,change,undone,record,start,end\n");
+
+    for (entry, undone) in history.newest_first() {
+        let (start, end) = entry.change.span();
+        push_row(
+            &mut csv,
+            &[
+                &timestamp(entry.at),
+                entry.change.name(),
+                &undone.to_string(),
+                &entry
+                    .change
+                    .record()
+                    .map(|record| record.id.to_string())
+                    .unwrap_or_default(),
+                &timestamp(start),
+                &end.map(timestamp).unwrap_or_default(),
+            ],
+        );
+    }
+
+    csv
+}
+
+fn tags(task: &Task) -> String {
+    task.tags
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+fn push_group_rows<'a>(csv: &mut String, breakdown: &'a Breakdown, keys: &mut Vec<&'a str>) {
+    for group in &breakdown.groups {
+        keys.push(&group.key);
+
+        if group.breakdown.groups.is_empty() {
+            let seconds = seconds(group.breakdown.total);
+            let mut row = keys.clone();
+            row.push(&seconds);
+            push_row(csv, &row);
+        } else {
+            push_group_rows(csv, &group.breakdown, keys);
+        }
+
+        keys.pop();
+    }
+}
+
+fn timestamp(instant: DateTime<Utc>) -> String {
+    instant.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+fn seconds(duration: Duration) -> String {
+    duration.as_secs().to_string()
+}
+
+fn push_row(csv: &mut String, fields: &[&str]) {
+    let row = fields.iter().map(|field| escape(field)).collect::<Vec<_>>();
+    csv.push_str(&row.join(","));
+    csv.push('\n');
+}
+
+/// Quotes a field if it contains a separator, quote or line break.
+fn escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::feature::tracker::{Group, RecordId};
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn records_are_one_row_each() {
+        let id = RecordId::new();
+        let entries = vec![ReportEntry {
+            id: Some(id),
+            start: utc("2026-10-15T09:00:00Z").into(),
+            end: Some(utc("2026-10-15T10:30:00Z").into()),
+            duration: Duration::from_secs(5400),
+            task: Task {
+                project: Some("website".to_string()),
+                description: Some("fix \"header\", again".to_string()),
+                tags: ["css".to_string(), "bug".to_string()].into(),
+            },
+        }];
+
+        assert_eq!(
+            records(&entries),
+            format!(
+                "start,end,duration_seconds,project,description,tags,id\n\
+                2026-10-15T09:00:00Z,2026-10-15T10:30:00Z,5400,website,\"fix \"\"header\"\", again\",bug;css,{id}\n"
+            )
+        );
+    }
+
+    #[test]
+    fn breakdown_has_one_row_per_innermost_group() {
+        let leaf = |key: &str, secs: u64| Group {
+            key: key.to_string(),
+            breakdown: Breakdown {
+                total: Duration::from_secs(secs),
+                groups: Vec::new(),
+            },
+        };
+        let breakdown = Breakdown {
+            total: Duration::from_secs(3 * 3600),
+            groups: vec![Group {
+                key: "website".to_string(),
+                breakdown: Breakdown {
+                    total: Duration::from_secs(3 * 3600),
+                    groups: vec![leaf("2026-10-14", 3600), leaf("2026-10-15", 2 * 3600)],
+                },
+            }],
+        };
+
+        assert_eq!(
+            super::breakdown(&breakdown, &[GroupBy::Project, GroupBy::Day]),
+            "project,day,duration_seconds\n\
+            website,2026-10-14,3600\n\
+            website,2026-10-15,7200\n"
+        );
+    }
+}
@@ -0,0 +1,365 @@
+//! JSON output, see the README for the schema
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::feature::tracker::{Breakdown, GroupBy, History, ReportEntry, ReportWindow, Session};
+
+/// Version of the JSON schema described in the README.
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize)]
+struct Report<'a> {
+    schema: u32,
+    window: Window,
+    total_seconds: u64,
+    running: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    records: Option<Vec<Record<'a>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    group_by: Option<Vec<&'static str>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    groups: Option<Vec<Group<'a>>>,
+}
+
+#[derive(Debug, Serialize)]
+struct Window {
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct Record<'a> {
+    id: Option<String>,
+    start: DateTime<Utc>,
+    end: Option<DateTime<Utc>>,
+    duration_seconds: u64,
+    project: Option<&'a str>,
+    description: Option<&'a str>,
+    tags: Vec<&'a str>,
+}
+
+impl<'a> From<&'a ReportEntry> for Record<'a> {
+    fn from(entry: &'a ReportEntry) -> Self {
+        Record {
+            id: entry.id.map(|id| id.to_string()),
+            start: entry.start.instant(),
+            end: entry.end.map(|end| end.instant()),
+            duration_seconds: entry.duration.as_secs(),
+            project: entry.task.project.as_deref(),
+            description: entry.task.description.as_deref(),
+            tags: entry.task.tags.iter().map(String::as_str).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Group<'a> {
+    key: &'a str,
+    total_seconds: u64,
+    groups: Vec<Group<'a>>,
+}
+
+fn groups(breakdown: &Breakdown) -> Vec<Group<'_>> {
+    breakdown
+        .groups
+        .iter()
+        .map(|group| Group {
+            key: &group.key,
+            total_seconds: group.breakdown.total.as_secs(),
+            groups: groups(&group.breakdown),
+        })
+        .collect()
+}
+
+fn window(window: &ReportWindow) -> Window {
+    Window {
+        start: window.start,
+        end: window.end,
+    }
+}
+
+fn running(entries: &[ReportEntry]) -> bool {
+    entries.iter().any(|entry| entry.end.is_none())
+}
+
+/// A report listing every record in the window.
+pub fn records_report(
+    report_window: &ReportWindow,
+    entries: &[ReportEntry],
+) -> serde_json::Result<String> {
+    let total = entries.iter().map(|entry| entry.duration).sum::<std::time::Duration>();
+
+    serde_json::to_string_pretty(&Report {
+        schema: SCHEMA_VERSION,
+        window: window(report_window),
+        total_seconds: total.as_secs(),
+        running: running(entries),
+        records: Some(entries.iter().map(Record::from).collect()),
+        group_by: None,
+        groups: None,
+    })
+}
+
+/// A report with the grouped breakdown of the window.
+pub fn breakdown_report(
+    report_window: &ReportWindow,
+    breakdown: &Breakdown,
+    group_by: &[GroupBy],
+    running: bool,
+) -> serde_json::Result<String> {
+    serde_json::to_string_pretty(&Report {
+        schema: SCHEMA_VERSION,
+        window: window(report_window),
+        total_seconds: breakdown.total.as_secs(),
+        running,
+        records: None,
+        group_by: Some(group_by.iter().map(GroupBy::name).collect()),
+        groups: Some(groups(breakdown)),
+    })
+}
+
+#[derive(Debug, Serialize)]
+struct Status<'a> {
+    schema: u32,
+    running: bool,
+    paused: bool,
+    start: Option<DateTime<Utc>>,
+    elapsed_seconds: Option<u64>,
+    project: Option<&'a str>,
+    description: Option<&'a str>,
+    tags: Vec<&'a str>,
+    stale_reasons: Vec<String>,
+}
+
+/// State of the tracker and the running session.
+pub fn status(session: Option<&Session>, now: DateTime<Utc>) -> serde_json::Result<String> {
+    let elapsed = session.map(|session| session.tracked(now).num_seconds().max(0) as u64);
+
+    serde_json::to_string_pretty(&Status {
+        schema: SCHEMA_VERSION,
+        running: session.is_some(),
+        paused: session.is_some_and(Session::is_paused),
+        start: session.map(|session| session.start.instant()),
+        elapsed_seconds: elapsed,
+        project: session.and_then(|session| session.task.project.as_deref()),
+        description: session.and_then(|session| session.task.description.as_deref()),
+        tags: session
+            .map(|session| session.task.tags.iter().map(String::as_str).collect())
+            .unwrap_or_default(),
+        stale_reasons: session
+            .map(|session| session.stale_reasons(now).iter().map(ToString::to_string).collect())
+            .unwrap_or_default(),
+    })
+}
+
+#[derive(Debug, Serialize)]
+struct HistoryDocument {
+    schema: u32,
+    changes: Vec<HistoryChange>,
+}
+
+#[derive(Debug, Serialize)]
+struct HistoryChange {
+    at: DateTime<Utc>,
+    change: &'static str,
+    undone: bool,
+    record: Option<String>,
+    start: DateTime<Utc>,
+    end: Option<DateTime<Utc>>,
+}
+
+/// The changes that can be undone and redone, newest first.
+pub fn history(history: &History) -> serde_json::Result<String> {
+    let changes = history
+        .newest_first()
+        .map(|(entry, undone)| {
+            let (start, end) = entry.change.span();
+            HistoryChange {
+                at: entry.at,
+                change: entry.change.name(),
+                undone,
+                record: entry.change.record().map(|record| record.id.to_string()),
+                start,
+                end,
+            }
+        })
+        .collect();
+
+    serde_json::to_string_pretty(&HistoryDocument {
+        schema: SCHEMA_VERSION,
+        changes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::feature::tracker::{Group as BreakdownGroup, Task};
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    fn day() -> ReportWindow {
+        ReportWindow {
+            start: utc("2026-10-15T00:00:00Z"),
+            end: utc("2026-10-16T00:00:00Z"),
+        }
+    }
+
+    #[test]
+    fn breakdown_report_matches_schema() {
+        let breakdown = Breakdown {
+            total: Duration::from_secs(5400),
+            groups: vec![BreakdownGroup {
+                key: "website".to_string(),
+                breakdown: Breakdown {
+                    total: Duration::from_secs(5400),
+                    groups: Vec::new(),
+                },
+            }],
+        };
+
+        let report = breakdown_report(&day(), &breakdown, &[GroupBy::Project], false).unwrap();
+
+        assert_eq!(
+            serde_json::from_str::<Value>(&report).unwrap(),
+            json!({
+                "schema": 1,
+                "window": { "start": "2026-10-15T00:00:00Z", "end": "2026-10-16T00:00:00Z" },
+                "total_seconds": 5400,
+                "running": false,
+                "group_by": ["project"],
+                "groups": [{ "key": "website", "total_seconds": 5400, "groups": [] }]
+            })
+        );
+    }
+
+    #[test]
+    fn records_report_marks_running_session() {
+        let entries = vec![ReportEntry {
+            id: None,
+            start: utc("2026-10-15T09:00:00Z").into(),
+            end: None,
+            duration: Duration::from_secs(60),
+            task: Task {
+                project: Some("website".to_string()),
+                ..Default::default()
+            },
+        }];
+
+        let report = records_report(&day(), &entries).unwrap();
+
+        assert_eq!(
+            serde_json::from_str::<Value>(&report).unwrap(),
+            json!({
+                "schema": 1,
+                "window": { "start": "2026-10-15T00:00:00Z", "end": "2026-10-16T00:00:00Z" },
+                "total_seconds": 60,
+                "running": true,
+                "records": [{
+                    "id": null,
+                    "start": "2026-10-15T09:00:00Z",
+                    "end": null,
+                    "duration_seconds": 60,
+                    "project": "website",
+                    "description": null,
+                    "tags": []
+                }]
+            })
+        );
+    }
+
+    #[test]
+    fn history_lists_newest_change_first() {
+        use crate::feature::tracker::{Change, JournalEntry};
+
+        let session = Session::new(utc("2026-10-15T09:00:00Z").into(), Task::default());
+        let record = session.clone().finish(utc("2026-10-15T10:30:00Z").into()).unwrap();
+        let history = History {
+            done: vec![JournalEntry {
+                at: utc("2026-10-15T09:00:00Z"),
+                change: Change::Start { session: session.clone() },
+            }],
+            undone: vec![JournalEntry {
+                at: utc("2026-10-15T10:30:00Z"),
+                change: Change::Stop { session, record: record.clone() },
+            }],
+        };
+
+        let history = super::history(&history).unwrap();
+
+        assert_eq!(
+            serde_json::from_str::<Value>(&history).unwrap(),
+            json!({
+                "schema": 1,
+                "changes": [
+                    {
+                        "at": "2026-10-15T10:30:00Z",
+                        "change": "stop",
+                        "undone": true,
+                        "record": record.id.to_string(),
+                        "start": "2026-10-15T09:00:00Z",
+                        "end": "2026-10-15T10:30:00Z"
+                    },
+                    {
+                        "at": "2026-10-15T09:00:00Z",
+                        "change": "start",
+                        "undone": false,
+                        "record": null,
+                        "start": "2026-10-15T09:00:00Z",
+                        "end": null
+                    }
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn status_of_stopped_tracker() {
+        let status = status(None, utc("2026-10-15T09:00:00Z")).unwrap();
+
+        assert_eq!(
+            serde_json::from_str::<Value>(&status).unwrap(),
+            json!({
+                "schema": 1,
+                "running": false,
+                "paused": false,
+                "start": null,
+                "elapsed_seconds": null,
+                "project": null,
+                "description": null,
+                "tags": [],
+                "stale_reasons": []
+            })
+        );
+    }
+
+    #[test]
+    fn status_of_paused_session_leaves_out_paused_time() {
+        let mut session = Session::new(utc("2026-10-15T09:00:00Z").into(), Task::default());
+        session.pause(utc("2026-10-15T09:30:00Z")).unwrap();
+
+        let status = status(Some(&session), utc("2026-10-15T10:00:00Z")).unwrap();
+
+        assert_eq!(
+            serde_json::from_str::<Value>(&status).unwrap(),
+            json!({
+                "schema": 1,
+                "running": true,
+                "paused": true,
+                "start": "2026-10-15T09:00:00Z",
+                "elapsed_seconds": 1800,
+                "project": null,
+                "description": null,
+                "tags": [],
+                "stale_reasons": []
+            })
+        );
+    }
+}
@@ -1,5 +1,13 @@
+pub mod csv;
+pub mod json;
+
 use std::time::Duration;
 
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+
+use crate::feature::tracker::{Breakdown, GroupBy, History, JournalEntry, ReportEntry};
+
 #[derive(Debug, Default)]
 pub struct HMSFormatter;
 
@@ -18,6 +26,145 @@ impl DurationFormatter for HMSFormatter {
     }
 }
 
+/// Renders a breakdown as a table. Every group gets a row with its subtotal,
+/// and the last row holds the grand total.
+pub fn breakdown_table<F>(breakdown: &Breakdown, group_by: &[GroupBy], formatter: &F) -> String
+where
+    F: DurationFormatter + ?Sized,
+{
+    let mut header = group_by
+        .iter()
+        .map(|group| group.name().to_uppercase())
+        .collect::<Vec<_>>();
+    header.push("DURATION".to_string());
+
+    let mut rows = vec![header];
+    push_group_rows(&mut rows, breakdown, 0, group_by.len(), formatter);
+
+    let mut total = vec![String::new(); group_by.len()];
+    if let Some(first) = total.first_mut() {
+        *first = "TOTAL".to_string();
+    }
+    total.push(formatter.format(breakdown.total));
+    rows.push(total);
+
+    render_table(&rows)
+}
+
+fn push_group_rows<F>(
+    rows: &mut Vec<Vec<String>>,
+    breakdown: &Breakdown,
+    depth: usize,
+    columns: usize,
+    formatter: &F,
+) where
+    F: DurationFormatter + ?Sized,
+{
+    for group in &breakdown.groups {
+        let mut row = vec![String::new(); columns];
+        row[depth] = group.key.clone();
+        row.push(formatter.format(group.breakdown.total));
+        rows.push(row);
+
+        push_group_rows(rows, &group.breakdown, depth + 1, columns, formatter);
+    }
+}
+
+/// Renders records as a table, one row per record in the given order. The
+/// end of a record that spans midnight includes its date, and the running
+/// session has no ID.
+pub fn log_table<F>(entries: &[ReportEntry], tz: Tz, formatter: &F) -> String
+where
+    F: DurationFormatter + ?Sized,
+{
+    let mut rows = vec![["ID", "DATE", "START", "END", "DURATION", "PROJECT", "TAGS", "DESCRIPTION"]
+        .map(String::from)
+        .to_vec()];
+
+    for entry in entries {
+        let start = entry.start.instant().with_timezone(&tz);
+        let end = match entry.end {
+            Some(end) => {
+                let end = end.instant().with_timezone(&tz);
+                if end.date_naive() == start.date_naive() {
+                    end.format("%H:%M").to_string()
+                } else {
+                    end.format("%Y-%m-%d %H:%M").to_string()
+                }
+            }
+            None => "running".to_string(),
+        };
+
+        rows.push(vec![
+            entry.id.map(|id| id.short()).unwrap_or_default(),
+            start.format("%Y-%m-%d").to_string(),
+            start.format("%H:%M").to_string(),
+            end,
+            formatter.format(entry.duration),
+            entry.task.project.clone().unwrap_or_default(),
+            entry.task.tags.iter().cloned().collect::<Vec<_>>().join(","),
+            entry.task.description.clone().unwrap_or_default(),
+        ]);
+    }
+
+    render_table(&rows)
+}
+
+/// Renders the journal as a table, newest change first. Changes that were
+/// undone, and can be redone, are marked.
+pub fn history_table(history: &History, tz: Tz) -> String {
+    let local = |instant: DateTime<Utc>| instant.with_timezone(&tz).format("%Y-%m-%d %H:%M").to_string();
+    let row = |entry: &JournalEntry, undone: bool| {
+        let (start, end) = entry.change.span();
+        let change = if undone {
+            format!("{} (undone)", entry.change.name())
+        } else {
+            entry.change.name().to_string()
+        };
+
+        vec![
+            local(entry.at),
+            change,
+            entry.change.record().map(|record| record.id.short()).unwrap_or_default(),
+            local(start),
+            end.map(local).unwrap_or_default(),
+        ]
+    };
+
+    let mut rows = vec![["WHEN", "CHANGE", "RECORD", "START", "END"].map(String::from).to_vec()];
+    rows.extend(history.newest_first().map(|(entry, undone)| row(entry, undone)));
+
+    render_table(&rows)
+}
+
+/// Lays out rows in left aligned columns separated by two spaces.
+pub fn render_table(rows: &[Vec<String>]) -> String {
+    let columns = rows.iter().map(Vec::len).max().unwrap_or_default();
+    let widths = (0..columns)
+        .map(|column| {
+            rows.iter()
+                .filter_map(|row| row.get(column))
+                .map(|cell| cell.chars().count())
+                .max()
+                .unwrap_or_default()
+        })
+        .collect::<Vec<_>>();
+
+    let mut table = String::new();
+    for row in rows {
+        let line = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell:<width$}"))
+            .collect::<Vec<_>>()
+            .join("  ");
+        table.push_str(line.trim_end());
+        table.push('\n');
+    }
+
+    table
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -26,10 +173,122 @@ mod tests {
     fn formats_seconds() {
         let duration = Duration::from_secs(5);
 
-        let formatter = HMSFormatter::default();
+        let formatter = HMSFormatter;
 
         let text = formatter.format(duration);
 
         assert_eq!(text, "00:00:05");
     }
+
+    #[test]
+    fn history_table_lists_newest_change_first() {
+        use crate::feature::tracker::{Change, RecordId, Session, Task, TimeRecord};
+
+        let utc = |s: &str| DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc);
+        let session = Session::new(utc("2026-10-15T09:00:00Z").into(), Task::default());
+        let record = TimeRecord {
+            id: RecordId::new(),
+            start: session.start,
+            end: utc("2026-10-15T10:30:00Z").into(),
+            pauses: Vec::new(),
+            task: Task::default(),
+        };
+        let entry = |at: &str, change: Change| JournalEntry {
+            at: utc(at),
+            change,
+        };
+        let history = History {
+            done: vec![
+                entry("2026-10-15T09:00:00Z", Change::Start { session: session.clone() }),
+                entry("2026-10-15T10:30:00Z", Change::Stop { session, record: record.clone() }),
+            ],
+            undone: vec![entry("2026-10-15T11:00:00Z", Change::Delete { record: record.clone() })],
+        };
+
+        let id = record.id.short();
+        assert_eq!(
+            history_table(&history, Tz::Europe__Berlin),
+            format!(
+                "WHEN              CHANGE           RECORD    START             END\n\
+                2026-10-15 13:00  delete (undone)  {id}  2026-10-15 11:00  2026-10-15 12:30\n\
+                2026-10-15 12:30  stop             {id}  2026-10-15 11:00  2026-10-15 12:30\n\
+                2026-10-15 11:00  start                      2026-10-15 11:00\n"
+            )
+        );
+    }
+
+    #[test]
+    fn log_table_shows_one_record_per_row() {
+        use crate::feature::tracker::{RecordId, Task};
+
+        let utc = |s: &str| DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc);
+        let id = RecordId::new();
+        let entries = [
+            ReportEntry {
+                id: Some(id),
+                start: utc("2026-10-15T21:00:00Z").into(),
+                end: Some(utc("2026-10-15T22:30:00Z").into()),
+                duration: Duration::from_secs(90 * 60),
+                task: Task {
+                    project: Some("website".to_string()),
+                    description: Some("fix header".to_string()),
+                    tags: ["bug", "css"].map(String::from).into(),
+                },
+            },
+            ReportEntry {
+                id: None,
+                start: utc("2026-10-16T08:00:00Z").into(),
+                end: None,
+                duration: Duration::from_secs(15 * 60),
+                task: Task::default(),
+            },
+        ];
+
+        let id = id.short();
+        assert_eq!(
+            log_table(&entries, Tz::Europe__Berlin, &HMSFormatter),
+            format!(
+                "ID        DATE        START  END               DURATION  PROJECT  TAGS     DESCRIPTION\n\
+                {id}  2026-10-15  23:00  2026-10-16 00:30  01:30:00  website  bug,css  fix header\n\
+                {:10}2026-10-16  10:00  running           00:15:00\n",
+                ""
+            )
+        );
+    }
+
+    #[test]
+    fn breakdown_table_has_subtotals_and_total() {
+        use crate::feature::tracker::Group;
+
+        let leaf = |key: &str, secs: u64| Group {
+            key: key.to_string(),
+            breakdown: Breakdown {
+                total: Duration::from_secs(secs),
+                groups: Vec::new(),
+            },
+        };
+        let breakdown = Breakdown {
+            total: Duration::from_secs(3 * 3600),
+            groups: vec![Group {
+                key: "website".to_string(),
+                breakdown: Breakdown {
+                    total: Duration::from_secs(3 * 3600),
+                    groups: vec![leaf("2026-10-14", 3600), leaf("2026-10-15", 2 * 3600)],
+                },
+            }],
+        };
+
+        let table = breakdown_table(&breakdown, &[GroupBy::Project, GroupBy::Day], &HMSFormatter);
+
+        assert_eq!(
+            table,
+            "\
+PROJECT  DAY         DURATION
+website              03:00:00
+         2026-10-14  01:00:00
+         2026-10-15  02:00:00
+TOTAL                03:00:00
+"
+        );
+    }
 }
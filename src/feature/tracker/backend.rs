@@ -0,0 +1,519 @@
+// storage backends chosen at run time
+// `AnyTracker` dispatches to one of the backends, since `Tracker` cannot be
+// used as a trait object. Backends are named by their kind, e.g. "sqlite",
+// or with their database as "KIND:PATH", e.g. "sqlite:records.db".
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+use chrono::TimeDelta;
+use error_stack::{Report, Result, ResultExt};
+
+use crate::error::Suggestion;
+
+use super::{
+    EndTime, FlatFileTracker, JsonlTracker, MemoryTracker, RecordId, ReportConfig, ReportTimespan, Reporter, ReporterError, Session,
+    SqliteTracker, StartTime, StartupStatus, StorageLock, Task, TimeRecord, Tracker, TrackerError,
+};
+
+/// How records are stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackendKind {
+    /// One JSON document, see [`FlatFileTracker`]
+    FlatFile,
+    /// One JSON line per change, see [`JsonlTracker`]
+    Jsonl,
+    /// SQLite database, see [`SqliteTracker`]
+    Sqlite,
+    /// Nothing is stored, see [`MemoryTracker`]
+    Memory,
+}
+
+impl BackendKind {
+    /// Every backend, the default first.
+    pub const ALL: [BackendKind; 4] = [
+        BackendKind::FlatFile,
+        BackendKind::Jsonl,
+        BackendKind::Sqlite,
+        BackendKind::Memory,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BackendKind::FlatFile => "flatfile",
+            BackendKind::Jsonl => "jsonl",
+            BackendKind::Sqlite => "sqlite",
+            BackendKind::Memory => "memory",
+        }
+    }
+
+    /// Name of the database file in the data directory, `None` for backends
+    /// that store nothing.
+    pub fn file_name(&self) -> Option<&'static str> {
+        match self {
+            BackendKind::FlatFile => Some("records.json"),
+            BackendKind::Jsonl => Some("records.jsonl"),
+            BackendKind::Sqlite => Some("records.db"),
+            BackendKind::Memory => None,
+        }
+    }
+
+    /// Names of all backends, e.g. for messages.
+    pub fn names() -> String {
+        BackendKind::ALL.map(|kind| kind.name()).join(", ")
+    }
+
+    /// Whether the running session is kept in the lockfile rather than with
+    /// the records.
+    pub fn uses_lockfile(&self) -> bool {
+        match self {
+            BackendKind::FlatFile | BackendKind::Jsonl => true,
+            BackendKind::Sqlite | BackendKind::Memory => false,
+        }
+    }
+}
+
+impl fmt::Display for BackendKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("unknown backend '{0}', expected one of: {names}", names = BackendKind::names())]
+pub struct UnknownBackend(String);
+
+impl FromStr for BackendKind {
+    type Err = UnknownBackend;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let name = s.trim().to_ascii_lowercase();
+
+        BackendKind::ALL
+            .into_iter()
+            .find(|kind| kind.name() == name)
+            .ok_or_else(|| UnknownBackend(s.to_string()))
+    }
+}
+
+/// A backend and where it stores its records, e.g. "sqlite:records.db". The
+/// path of the memory backend is not used and may be left out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackendSpec {
+    pub kind: BackendKind,
+    pub path: PathBuf,
+}
+
+impl BackendSpec {
+    /// Opens the backend. Backends that keep the running session in a
+    /// lockfile use `lockfile`.
+    pub fn open(&self, lockfile: &Path) -> AnyTracker {
+        match self.kind {
+            BackendKind::FlatFile => AnyTracker::FlatFile(FlatFileTracker::new(&self.path, lockfile)),
+            BackendKind::Jsonl => AnyTracker::Jsonl(JsonlTracker::new(&self.path, lockfile)),
+            BackendKind::Sqlite => AnyTracker::Sqlite(SqliteTracker::new(&self.path)),
+            BackendKind::Memory => AnyTracker::Memory(MemoryTracker::default()),
+        }
+    }
+
+    /// Whether both specs name the same database file, however their paths
+    /// are spelled. Memory backends never share a database.
+    pub fn same_database(&self, other: &BackendSpec) -> bool {
+        let stored = |spec: &BackendSpec| spec.kind.file_name().is_some();
+        stored(self) && stored(other) && resolve(&self.path) == resolve(&other.path)
+    }
+}
+
+/// The canonical form of a path whose file may not exist yet.
+fn resolve(path: &Path) -> PathBuf {
+    if let Ok(path) = path.canonicalize() {
+        return path;
+    }
+
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    match (parent.canonicalize(), path.file_name()) {
+        (Ok(parent), Some(name)) => parent.join(name),
+        _ => path.to_path_buf(),
+    }
+}
+
+impl fmt::Display for BackendSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.kind, self.path.display())
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum InvalidBackendSpec {
+    #[error("expected KIND:PATH, e.g. sqlite:records.db, got '{0}'")]
+    MissingPath(String),
+    #[error(transparent)]
+    UnknownBackend(#[from] UnknownBackend),
+}
+
+impl FromStr for BackendSpec {
+    type Err = InvalidBackendSpec;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let (kind, path) = s.split_once(':').unwrap_or((s, ""));
+        let kind = kind.parse()?;
+        if path.is_empty() && kind != BackendKind::Memory {
+            return Err(InvalidBackendSpec::MissingPath(s.to_string()));
+        }
+
+        Ok(BackendSpec {
+            kind,
+            path: PathBuf::from(path),
+        })
+    }
+}
+
+/// One of the backends, picked at run time.
+pub enum AnyTracker {
+    FlatFile(FlatFileTracker),
+    Jsonl(JsonlTracker),
+    Sqlite(SqliteTracker),
+    Memory(MemoryTracker),
+}
+
+macro_rules! dispatch {
+    ($self:expr, $tracker:ident => $body:expr) => {
+        match $self {
+            AnyTracker::FlatFile($tracker) => $body,
+            AnyTracker::Jsonl($tracker) => $body,
+            AnyTracker::Sqlite($tracker) => $body,
+            AnyTracker::Memory($tracker) => $body,
+        }
+    };
+}
+
+impl Tracker for AnyTracker {
+    fn lock_storage(&self) -> Result<StorageLock, TrackerError> {
+        dispatch!(self, tracker => tracker.lock_storage())
+    }
+
+    fn start_at(&mut self, task: Task, start: StartTime) -> Result<StartupStatus, TrackerError> {
+        dispatch!(self, tracker => tracker.start_at(task, start))
+    }
+
+    fn is_running(&self) -> bool {
+        dispatch!(self, tracker => tracker.is_running())
+    }
+
+    fn session(&self) -> Result<Option<Session>, TrackerError> {
+        dispatch!(self, tracker => tracker.session())
+    }
+
+    fn stop_at(&mut self, end: EndTime) -> Result<TimeRecord, TrackerError> {
+        dispatch!(self, tracker => tracker.stop_at(end))
+    }
+
+    fn switch_at(&mut self, task: Task, at: StartTime) -> Result<Option<TimeRecord>, TrackerError> {
+        dispatch!(self, tracker => tracker.switch_at(task, at))
+    }
+
+    fn restore_session(&mut self, session: Option<Session>) -> Result<(), TrackerError> {
+        dispatch!(self, tracker => tracker.restore_session(session))
+    }
+
+    fn has_records(&self) -> Result<bool, TrackerError> {
+        dispatch!(self, tracker => tracker.has_records())
+    }
+
+    fn records(&self) -> Result<impl Iterator<Item = TimeRecord>, TrackerError> {
+        let records: Box<dyn Iterator<Item = TimeRecord>> = dispatch!(self, tracker => Box::new(tracker.records()?));
+        Ok(records)
+    }
+
+    fn add(&mut self, record: TimeRecord) -> Result<Vec<TimeRecord>, TrackerError> {
+        dispatch!(self, tracker => tracker.add(record))
+    }
+
+    fn import<I>(&mut self, records: I) -> Result<usize, TrackerError>
+    where
+        I: IntoIterator<Item = TimeRecord>,
+    {
+        dispatch!(self, tracker => tracker.import(records))
+    }
+
+    fn clear(&mut self) -> Result<(), TrackerError> {
+        dispatch!(self, tracker => tracker.clear())
+    }
+
+    fn replace(&mut self, record: TimeRecord) -> Result<TimeRecord, TrackerError> {
+        dispatch!(self, tracker => tracker.replace(record))
+    }
+
+    fn delete(&mut self, id: RecordId) -> Result<TimeRecord, TrackerError> {
+        dispatch!(self, tracker => tracker.delete(id))
+    }
+}
+
+impl Reporter for AnyTracker {
+    fn total_duration(&self, timespan: ReportTimespan, config: &ReportConfig) -> Result<Duration, ReporterError> {
+        dispatch!(self, tracker => tracker.total_duration(timespan, config))
+    }
+}
+
+/// What [`convert`] copied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Conversion {
+    pub records: usize,
+    /// Tracked time of all records
+    pub duration: TimeDelta,
+    /// Whether a running session was copied
+    pub session: bool,
+}
+
+/// Copies every record from `source` into `target`, and the running session
+/// unless both keep it in the same lockfile (`copy_session` is false). Fails
+/// without copying if `target` already holds records or a session, unless
+/// `force` is set to clear it first. The copy is checked against the source
+/// afterwards, and neither changes in between.
+pub fn convert<S, T>(source: &mut S, target: &mut T, copy_session: bool, force: bool) -> Result<Conversion, TrackerError>
+where
+    S: Tracker,
+    T: Tracker,
+{
+    source.atomically(|source| target.atomically(|target| copy(source, target, copy_session, force)))
+}
+
+fn copy<S, T>(source: &S, target: &mut T, copy_session: bool, force: bool) -> Result<Conversion, TrackerError>
+where
+    S: Tracker,
+    T: Tracker,
+{
+    // An unreadable target is an error rather than empty, so it is not cleared
+    let occupied = target.has_records()? || (copy_session && target.session()?.is_some());
+    if occupied && !force {
+        return Err(Report::new(TrackerError))
+            .attach_printable("the target already holds records or a running session")
+            .attach(Suggestion("use --force to replace them"));
+    }
+
+    // Read before clearing, in case both are the same database after all
+    let records = source.records()?.collect::<Vec<_>>();
+    let copied = summarize(records.iter().cloned());
+
+    // Clearing keeps the session, so a shared lockfile is never touched
+    if occupied {
+        target.clear().attach_printable("unable to clear the target")?;
+    }
+    target.import(records).attach_printable("unable to copy records")?;
+
+    verify(&copied, &summarize(target.records()?))?;
+
+    let session = match copy_session {
+        true => source.session()?,
+        false => None,
+    };
+    if copy_session {
+        target
+            .restore_session(session.clone())
+            .attach_printable("unable to copy running session")?;
+        if target.session()?.map(|session| session.task) != session.as_ref().map(|session| session.task.clone()) {
+            return Err(Report::new(TrackerError)).attach_printable("the running session was not copied");
+        }
+    }
+
+    Ok(Conversion {
+        records: copied.records,
+        duration: copied.duration,
+        session: session.is_some(),
+    })
+}
+
+/// Count and tracked time of records, to compare copies.
+#[derive(Debug, Default)]
+struct Summary {
+    records: usize,
+    /// Number of tracked intervals, each may lose precision when copied
+    intervals: usize,
+    duration: TimeDelta,
+}
+
+fn summarize<I>(records: I) -> Summary
+where
+    I: Iterator<Item = TimeRecord>,
+{
+    records.fold(Summary::default(), |mut summary, record| {
+        let active = record.active();
+        summary.records += 1;
+        summary.intervals += active.len();
+        summary.duration += active.into_iter().map(|(start, end)| end - start).sum::<TimeDelta>();
+        summary
+    })
+}
+
+/// Checks that a copy has the same records as the source. Backends that
+/// store milliseconds drop finer digits, so every interval may be off by up
+/// to a millisecond.
+fn verify(source: &Summary, copy: &Summary) -> Result<(), TrackerError> {
+    if copy.records != source.records {
+        return Err(Report::new(TrackerError)).attach_printable(format!(
+            "copied {} records, but the target holds {}",
+            source.records, copy.records
+        ));
+    }
+
+    let tolerance = TimeDelta::milliseconds(source.intervals as i64);
+    if (copy.duration - source.duration).abs() > tolerance {
+        return Err(Report::new(TrackerError)).attach_printable(format!(
+            "copied records with a total of {}s, but the target holds {}s",
+            source.duration.num_seconds(),
+            copy.duration.num_seconds()
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_fs::{prelude::PathChild, TempDir};
+    use chrono::{DateTime, Utc};
+
+    use super::*;
+    use crate::feature::tracker::Pause;
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    fn records() -> Vec<TimeRecord> {
+        vec![
+            TimeRecord {
+                id: RecordId::new(),
+                start: utc("2026-10-15T09:00:00.123456Z").into(),
+                end: utc("2026-10-15T10:00:00.654321Z").into(),
+                pauses: vec![Pause {
+                    start: utc("2026-10-15T09:30:00.5Z"),
+                    end: utc("2026-10-15T09:40:00Z"),
+                }],
+                task: Task {
+                    project: Some("website".to_string()),
+                    ..Default::default()
+                },
+            },
+            TimeRecord {
+                id: RecordId::new(),
+                start: utc("2026-10-15T11:00:00Z").into(),
+                end: utc("2026-10-15T12:00:00Z").into(),
+                pauses: Vec::new(),
+                task: Task::default(),
+            },
+        ]
+    }
+
+    fn open(temp: &TempDir, kind: BackendKind, name: &str) -> AnyTracker {
+        let spec = BackendSpec {
+            kind,
+            path: temp.child(name).to_path_buf(),
+        };
+        spec.open(&temp.child("lockfile"))
+    }
+
+    #[test]
+    fn parses_backend_spec() {
+        let spec = "sqlite:/tmp/records.db".parse::<BackendSpec>().unwrap();
+
+        assert_eq!(spec.kind, BackendKind::Sqlite);
+        assert_eq!(spec.path, Path::new("/tmp/records.db"));
+        assert_eq!(spec.to_string(), "sqlite:/tmp/records.db");
+        assert!("sqlite".parse::<BackendSpec>().is_err());
+        assert!("mysql:records.db".parse::<BackendSpec>().is_err());
+        assert_eq!("memory".parse::<BackendSpec>().unwrap().kind, BackendKind::Memory);
+    }
+
+    #[test]
+    fn same_database_is_found_however_it_is_spelled() {
+        let temp = TempDir::new().unwrap();
+        let spec = |kind, path: &Path| BackendSpec { kind, path: path.to_path_buf() };
+        let db = temp.child("db.sqlite");
+
+        // Also before the file exists
+        let dotted = temp.path().join(".").join("db.sqlite");
+        assert!(spec(BackendKind::Sqlite, &db).same_database(&spec(BackendKind::Sqlite, &dotted)));
+        std::fs::write(&db, "").unwrap();
+        assert!(spec(BackendKind::Sqlite, &db).same_database(&spec(BackendKind::Jsonl, &dotted)));
+
+        assert!(!spec(BackendKind::Sqlite, &db).same_database(&spec(BackendKind::Sqlite, &temp.child("other.db"))));
+        assert!(!spec(BackendKind::Memory, Path::new("")).same_database(&spec(BackendKind::Memory, Path::new(""))));
+    }
+
+    #[test]
+    fn converts_records_and_session_from_flatfile_to_sqlite() {
+        let temp = TempDir::new().unwrap();
+        let mut source = open(&temp, BackendKind::FlatFile, "db.json");
+        source.import(records()).unwrap();
+        source.start(Task::default()).unwrap();
+        let mut target = open(&temp, BackendKind::Sqlite, "db.sqlite");
+
+        let conversion = convert(&mut source, &mut target, true, false).unwrap();
+
+        // Sub-millisecond digits are lost in SQLite
+        assert_eq!(conversion.records, 2);
+        assert_eq!(conversion.duration, TimeDelta::minutes(110) + TimeDelta::microseconds(1_030_865));
+        assert_eq!(summarize(target.records().unwrap()).duration, TimeDelta::minutes(110) + TimeDelta::milliseconds(1_031));
+        assert!(conversion.session);
+        let ids = |tracker: &AnyTracker| tracker.records().unwrap().map(|record| record.id).collect::<Vec<_>>();
+        assert_eq!(ids(&target), ids(&source));
+        assert!(target.is_running());
+    }
+
+    #[test]
+    fn refuses_to_overwrite_a_target_unless_forced() {
+        let temp = TempDir::new().unwrap();
+        let mut source = open(&temp, BackendKind::Jsonl, "db.jsonl");
+        source.import(records()).unwrap();
+        let mut target = open(&temp, BackendKind::Sqlite, "db.sqlite");
+        let existing = records().pop().unwrap();
+        target.add(existing.clone()).unwrap();
+
+        assert!(convert(&mut source, &mut target, true, false).is_err());
+        assert_eq!(target.records().unwrap().collect::<Vec<_>>(), vec![existing.clone()]);
+
+        convert(&mut source, &mut target, true, true).unwrap();
+        assert_eq!(target.records().unwrap().count(), 2);
+        assert!(target.records().unwrap().all(|record| record.id != existing.id));
+    }
+
+    #[test]
+    fn failed_conversion_keeps_a_shared_session() {
+        let temp = TempDir::new().unwrap();
+        let mut source = open(&temp, BackendKind::FlatFile, "db.json");
+        source.start(Task::default()).unwrap();
+        let session = source.session().unwrap();
+        let mut target = open(&temp, BackendKind::Jsonl, "db.jsonl");
+        target.add(records().pop().unwrap()).unwrap();
+
+        // Given a source record the target rejects, since it ends before it starts
+        let mut invalid = records().remove(0);
+        invalid.end = utc("2026-10-15T08:00:00Z").into();
+        let db = serde_json::json!({ "version": 1, "records": [invalid] });
+        std::fs::write(temp.child("db.json"), db.to_string()).unwrap();
+
+        assert!(convert(&mut source, &mut target, false, true).is_err());
+        assert_eq!(source.session().unwrap(), session);
+        assert_eq!(target.session().unwrap(), session);
+    }
+
+    #[test]
+    fn unreadable_target_is_not_overwritten() {
+        let temp = TempDir::new().unwrap();
+        let mut source = open(&temp, BackendKind::Sqlite, "db.sqlite");
+        source.import(records()).unwrap();
+        std::fs::write(temp.child("db.json"), "{\"records\": [").unwrap();
+        let mut target = open(&temp, BackendKind::FlatFile, "db.json");
+
+        assert!(convert(&mut source, &mut target, true, true).is_err());
+        assert_eq!(std::fs::read_to_string(temp.child("db.json")).unwrap(), "{\"records\": [");
+    }
+}
@@ -0,0 +1,314 @@
+use std::{collections::BTreeMap, fmt, str::FromStr, time::Duration};
+
+use chrono::{DateTime, Datelike, Months, TimeDelta, Utc};
+use chrono_tz::Tz;
+
+use super::{reporter::day_start, ReportConfig, ReportWindow, Task};
+
+/// What to group a breakdown by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupBy {
+    /// Calendar day, e.g. "2026-10-15"
+    Day,
+    /// ISO week, e.g. "2026-W42"
+    Week,
+    /// Calendar month, e.g. "2026-10"
+    Month,
+    /// Project name
+    Project,
+    /// Tag. Records with several tags are counted once per tag.
+    Tag,
+}
+
+impl GroupBy {
+    pub fn name(&self) -> &'static str {
+        match self {
+            GroupBy::Day => "day",
+            GroupBy::Week => "week",
+            GroupBy::Month => "month",
+            GroupBy::Project => "project",
+            GroupBy::Tag => "tag",
+        }
+    }
+}
+
+impl fmt::Display for GroupBy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("unknown grouping '{0}', expected one of: day, week, month, project, tag")]
+pub struct UnknownGroupBy(String);
+
+impl FromStr for GroupBy {
+    type Err = UnknownGroupBy;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "day" => Ok(GroupBy::Day),
+            "week" => Ok(GroupBy::Week),
+            "month" => Ok(GroupBy::Month),
+            "project" => Ok(GroupBy::Project),
+            "tag" => Ok(GroupBy::Tag),
+            _ => Err(UnknownGroupBy(s.to_string())),
+        }
+    }
+}
+
+/// Key used for records without a project.
+pub const NO_PROJECT: &str = "(no project)";
+/// Key used for records without tags.
+pub const NO_TAG: &str = "(untagged)";
+
+/// Tracked time, grouped into nested groups.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Breakdown {
+    /// Total time of this level. When grouping by tag, this can be less than
+    /// the sum of the groups, as a record counts towards each of its tags.
+    pub total: Duration,
+    /// Groups sorted by key, empty at the innermost level.
+    pub groups: Vec<Group>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Group {
+    pub key: String,
+    pub breakdown: Breakdown,
+}
+
+/// A part of a record that lies inside the report window.
+#[derive(Debug, Clone)]
+pub(crate) struct Segment {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub task: Task,
+}
+
+impl Segment {
+    /// Clips the interval to the window. Returns `None` if nothing is left.
+    pub fn clipped(
+        window: &ReportWindow,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        task: Task,
+    ) -> Option<Self> {
+        let start = start.max(window.start);
+        let end = end.min(window.end);
+
+        (start < end).then_some(Segment { start, end, task })
+    }
+
+    fn duration(&self) -> TimeDelta {
+        self.end - self.start
+    }
+}
+
+/// Groups the segments by each level of `group_by` in turn.
+pub(crate) fn breakdown(segments: Vec<Segment>, group_by: &[GroupBy], config: &ReportConfig) -> Breakdown {
+    let total = segments
+        .iter()
+        .map(Segment::duration)
+        .sum::<TimeDelta>()
+        .to_std()
+        .unwrap_or_default();
+
+    let Some((&level, rest)) = group_by.split_first() else {
+        return Breakdown {
+            total,
+            groups: Vec::new(),
+        };
+    };
+
+    let mut keyed: BTreeMap<String, Vec<Segment>> = BTreeMap::new();
+    for segment in segments {
+        for (key, segment) in split(segment, level, config.timezone) {
+            keyed.entry(key).or_default().push(segment);
+        }
+    }
+
+    let groups = keyed
+        .into_iter()
+        .map(|(key, segments)| Group {
+            key,
+            breakdown: breakdown(segments, rest, config),
+        })
+        .collect();
+
+    Breakdown { total, groups }
+}
+
+/// Splits a segment into the groups it belongs to at the given level.
+fn split(segment: Segment, level: GroupBy, tz: Tz) -> Vec<(String, Segment)> {
+    match level {
+        GroupBy::Project => {
+            let key = segment
+                .task
+                .project
+                .clone()
+                .unwrap_or_else(|| NO_PROJECT.to_string());
+            vec![(key, segment)]
+        }
+        GroupBy::Tag if segment.task.tags.is_empty() => vec![(NO_TAG.to_string(), segment)],
+        GroupBy::Tag => segment
+            .task
+            .tags
+            .iter()
+            .map(|tag| (tag.clone(), segment.clone()))
+            .collect(),
+        GroupBy::Day | GroupBy::Week | GroupBy::Month => {
+            let mut parts = Vec::new();
+            let mut rest = segment;
+            loop {
+                let (key, bucket_end) = calendar_bucket(level, rest.start, tz);
+                if rest.end <= bucket_end {
+                    parts.push((key, rest));
+                    return parts;
+                }
+
+                let mut part = rest.clone();
+                part.end = bucket_end;
+                rest.start = bucket_end;
+                parts.push((key, part));
+            }
+        }
+    }
+}
+
+/// The key of the calendar bucket containing `instant`, and when it ends.
+fn calendar_bucket(level: GroupBy, instant: DateTime<Utc>, tz: Tz) -> (String, DateTime<Utc>) {
+    let date = instant.with_timezone(&tz).date_naive();
+
+    match level {
+        GroupBy::Week => {
+            let week = date.iso_week();
+            let monday = date - chrono::Days::new(date.weekday().num_days_from_monday().into());
+            let key = format!("{}-W{:02}", week.year(), week.week());
+            (key, day_start(monday + chrono::Days::new(7), tz))
+        }
+        GroupBy::Month => {
+            let first = date.with_day(1).unwrap_or(date);
+            let key = first.format("%Y-%m").to_string();
+            (key, day_start(first + Months::new(1), tz))
+        }
+        _ => {
+            let key = date.to_string();
+            (key, day_start(date + chrono::Days::new(1), tz))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    fn segment(start: &str, end: &str, project: Option<&str>, tags: &[&str]) -> Segment {
+        Segment {
+            start: utc(start),
+            end: utc(end),
+            task: Task {
+                project: project.map(str::to_string),
+                description: None,
+                tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            },
+        }
+    }
+
+    fn keys(breakdown: &Breakdown) -> Vec<&str> {
+        breakdown.groups.iter().map(|group| group.key.as_str()).collect()
+    }
+
+    fn hours(hours: u64) -> Duration {
+        Duration::from_secs(hours * 3600)
+    }
+
+    #[test]
+    fn splits_records_across_midnight() {
+        let segments = vec![segment("2026-10-14T22:00:00Z", "2026-10-15T02:00:00Z", None, &[])];
+
+        let result = breakdown(segments, &[GroupBy::Day], &ReportConfig::default());
+
+        assert_eq!(keys(&result), ["2026-10-14", "2026-10-15"]);
+        assert_eq!(result.groups[0].breakdown.total, hours(2));
+        assert_eq!(result.groups[1].breakdown.total, hours(2));
+        assert_eq!(result.total, hours(4));
+    }
+
+    #[test]
+    fn days_follow_configured_timezone() {
+        let segments = vec![segment("2026-10-14T22:00:00Z", "2026-10-15T02:00:00Z", None, &[])];
+        let config = ReportConfig {
+            timezone: Tz::Asia__Tokyo,
+            ..Default::default()
+        };
+
+        let result = breakdown(segments, &[GroupBy::Day], &config);
+
+        assert_eq!(keys(&result), ["2026-10-15"]);
+    }
+
+    #[test]
+    fn groups_by_week_and_month() {
+        let segments = vec![
+            segment("2026-09-30T10:00:00Z", "2026-09-30T11:00:00Z", None, &[]),
+            segment("2026-10-01T10:00:00Z", "2026-10-01T12:00:00Z", None, &[]),
+            segment("2026-10-05T10:00:00Z", "2026-10-05T13:00:00Z", None, &[]),
+        ];
+        let config = ReportConfig::default();
+
+        let weeks = breakdown(segments.clone(), &[GroupBy::Week], &config);
+        let months = breakdown(segments, &[GroupBy::Month], &config);
+
+        assert_eq!(keys(&weeks), ["2026-W40", "2026-W41"]);
+        assert_eq!(weeks.groups[0].breakdown.total, hours(3));
+        assert_eq!(keys(&months), ["2026-09", "2026-10"]);
+        assert_eq!(months.groups[1].breakdown.total, hours(5));
+    }
+
+    #[test]
+    fn nests_groups() {
+        let segments = vec![
+            segment("2026-10-14T10:00:00Z", "2026-10-14T11:00:00Z", Some("website"), &[]),
+            segment("2026-10-15T10:00:00Z", "2026-10-15T12:00:00Z", Some("website"), &[]),
+            segment("2026-10-15T13:00:00Z", "2026-10-15T14:00:00Z", None, &[]),
+        ];
+
+        let result = breakdown(
+            segments,
+            &[GroupBy::Project, GroupBy::Day],
+            &ReportConfig::default(),
+        );
+
+        assert_eq!(keys(&result), [NO_PROJECT, "website"]);
+        let website = &result.groups[1].breakdown;
+        assert_eq!(website.total, hours(3));
+        assert_eq!(keys(website), ["2026-10-14", "2026-10-15"]);
+        assert_eq!(website.groups[1].breakdown.total, hours(2));
+        assert!(website.groups[1].breakdown.groups.is_empty());
+    }
+
+    #[test]
+    fn records_count_towards_each_tag() {
+        let segments = vec![
+            segment("2026-10-15T10:00:00Z", "2026-10-15T11:00:00Z", None, &["bug", "css"]),
+            segment("2026-10-15T12:00:00Z", "2026-10-15T13:00:00Z", None, &[]),
+        ];
+
+        let result = breakdown(segments, &[GroupBy::Tag], &ReportConfig::default());
+
+        assert_eq!(keys(&result), [NO_TAG, "bug", "css"]);
+        assert_eq!(result.total, hours(2));
+    }
+
+    #[test]
+    fn parses_group_names() {
+        assert_eq!("Day".parse::<GroupBy>().unwrap(), GroupBy::Day);
+        assert_eq!(" tag".parse::<GroupBy>().unwrap(), GroupBy::Tag);
+        assert!("year".parse::<GroupBy>().is_err());
+    }
+}
@@ -1,41 +1,106 @@
 // flat file tracker
 // 2 files:
 // - "lockfile": tracker is running
-// - "database.json": all records
-
-use error_stack::{Result, ResultExt};
+// - "database.json": all records, and the format version (see `migration`)
+//   ("database.json.bak" holds the previous version of the database,
+//   "database.json.v0.bak" the database as it was before it was migrated from
+//   format version 0, "database.json.lock" is locked while a process reads or
+//   changes the files)
+
+use chrono::{DateTime, Utc};
+use error_stack::{Report, Result, ResultExt};
 use serde::{Deserialize, Serialize};
 use std::{
+    collections::HashSet,
     fs::OpenOptions,
-    io::{Read, Write},
-    path::PathBuf,
+    io::{ErrorKind, Read, Write},
+    path::{Path, PathBuf},
+    time::Duration,
 };
 
-use super::{EndTime, Reporter, StartTime, StartupStatus, TimeRecord, Tracker, TrackerError};
-
-#[derive(Debug, thiserror::Error)]
-#[error("file system tracker error")]
-pub struct FlatFileTrackerError;
+use super::{
+    lock::{ReentrantLock, LOCK_TIMEOUT},
+    migration::{self, FORMAT_VERSION},
+    EndTime, Origin, Pause, RecordId, Reporter, Session, StartTime, StartupStatus, StorageLock, Task, TimeRecord, Tracker,
+    TrackerError,
+};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 struct LockfileData {
     start_time: StartTime,
+    #[serde(flatten)]
+    task: Task,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pauses: Vec<Pause>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    paused_at: Option<DateTime<Utc>>,
+    /// Hostname, PID and boot ID of the process that started the session
+    #[serde(flatten)]
+    origin: Option<Origin>,
+}
+
+impl From<LockfileData> for Session {
+    fn from(data: LockfileData) -> Self {
+        Session {
+            start: data.start_time,
+            task: data.task,
+            pauses: data.pauses,
+            paused_at: data.paused_at,
+            origin: data.origin,
+        }
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+impl From<Session> for LockfileData {
+    fn from(session: Session) -> Self {
+        LockfileData {
+            start_time: session.start,
+            task: session.task,
+            pauses: session.pauses,
+            paused_at: session.paused_at,
+            origin: session.origin,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 struct FlatFileDatabase {
+    /// Always [`FORMAT_VERSION`], older databases are migrated when read
+    version: u32,
     records: Vec<TimeRecord>,
 }
 
+impl Default for FlatFileDatabase {
+    fn default() -> Self {
+        FlatFileDatabase {
+            version: FORMAT_VERSION,
+            records: Vec::new(),
+        }
+    }
+}
+
 impl FlatFileDatabase {
     pub fn push(&mut self, value: TimeRecord) {
         self.records.push(value)
     }
+
+    fn position(&self, id: RecordId) -> Option<usize> {
+        self.records.iter().position(|record| record.id == id)
+    }
+
+    /// Index of the record with the given ID, or an error if there is none.
+    fn find(&self, id: RecordId) -> Result<usize, TrackerError> {
+        self.position(id)
+            .ok_or(TrackerError)
+            .attach_printable(format!("no record with ID {id}"))
+    }
 }
 
 pub struct FlatFileTracker {
     database: PathBuf,
     lockfile: PathBuf,
+    /// Held around every read and change, see [`FlatFileTracker::with_lock_timeout`]
+    lock: ReentrantLock,
 }
 
 impl Reporter for FlatFileTracker {}
@@ -48,117 +113,429 @@ impl FlatFileTracker {
     {
         let database = database.into();
         let lockfile = lockfile.into();
+        let lock = ReentrantLock::new(with_suffix(&database, ".lock"), LOCK_TIMEOUT);
 
-        FlatFileTracker { database, lockfile }
+        FlatFileTracker { database, lockfile, lock }
     }
-}
 
-impl Tracker for FlatFileTracker {
-    fn start(&mut self) -> Result<StartupStatus, TrackerError> {
-        if self.lockfile.exists() {
-            return Ok(StartupStatus::Running);
+    /// Sets how long to wait for other processes using the same database.
+    /// Their changes are serialized through a `.lock` file next to the
+    /// database, and waiting longer than this fails.
+    pub fn with_lock_timeout(mut self, timeout: Duration) -> Self {
+        self.lock.set_timeout(timeout);
+        self
+    }
+
+    /// Migrates the database to the current format version, or only tells
+    /// what would be done when `dry_run` is set. Other methods migrate the
+    /// database by themselves when they read it.
+    pub fn migrate(&mut self, dry_run: bool) -> Result<MigrationReport, TrackerError> {
+        let _lock = self.lock.acquire()?;
+        let (db, version, from_backup) = recover_database(&self.database)?;
+
+        let report = MigrationReport {
+            from: version,
+            to: FORMAT_VERSION,
+            migrations: migration::pending(version)?.iter().map(|m| m.description).collect(),
+            backup: (version < FORMAT_VERSION).then(|| migration_backup_path(&self.database, version)),
+        };
+        if !dry_run && from_backup {
+            restore_backup(&self.database)?;
+        }
+        if !dry_run && version < FORMAT_VERSION {
+            upgrade_database(&self.database, &db, version)?;
         }
 
-        let lockfile_data = {
-            let start_time = StartTime::now();
-            let data = LockfileData { start_time };
+        Ok(report)
+    }
+}
 
-            serde_json::to_string(&data)
-                .change_context(TrackerError)
-                .attach_printable("unable to serialize lockfile data when starting tracker")?
-        };
+/// What [`FlatFileTracker::migrate`] did or would do.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationReport {
+    /// Format version of the database before migrating
+    pub from: u32,
+    pub to: u32,
+    /// What each migration changes, in the order they are applied
+    pub migrations: Vec<&'static str>,
+    /// Copy of the database as it was, if it had to be migrated
+    pub backup: Option<PathBuf>,
+}
 
-        OpenOptions::new()
-            .write(true)
-            .create_new(true)
-            .open(&self.lockfile)
-            .change_context(TrackerError)
-            .attach_printable("unable to create new lockfile when starting tracker")?
-            .write_all(lockfile_data.as_bytes())
-            .change_context(TrackerError)
-            .attach_printable("unable to write lockfile data when starting tracker")?;
+impl Tracker for FlatFileTracker {
+    fn lock_storage(&self) -> Result<StorageLock, TrackerError> {
+        self.lock.acquire().map(StorageLock::from)
+    }
 
-        Ok(StartupStatus::Started)
+    fn start_at(&mut self, task: Task, start_time: StartTime) -> Result<StartupStatus, TrackerError> {
+        let _lock = self.lock.acquire()?;
+        start_session(&self.lockfile, Session::new(start_time, task))
     }
 
     fn is_running(&self) -> bool {
         self.lockfile.exists()
     }
 
-    fn stop(&mut self) -> Result<(), TrackerError> {
-        let start = read_lockfile(&self.lockfile)?.start_time;
-        let end = EndTime::now();
+    fn session(&self) -> Result<Option<Session>, TrackerError> {
+        let _lock = self.lock.acquire()?;
+        read_session(&self.lockfile)
+    }
 
-        let record = TimeRecord { start, end };
+    fn stop_at(&mut self, end: EndTime) -> Result<TimeRecord, TrackerError> {
+        let _lock = self.lock.acquire()?;
+        let record = Session::from(read_lockfile(&self.lockfile)?).finish(end)?;
 
         let mut db = load_database(&self.database)?;
-        db.push(record);
+        db.push(record.clone());
         save_database(&self.database, &db)?;
 
         std::fs::remove_file(&self.lockfile)
             .change_context(TrackerError)
             .attach_printable("unable to remove lockfile when stopping tracker")?;
 
-        Ok(())
+        Ok(record)
+    }
+
+    fn switch_at(&mut self, task: Task, at: StartTime) -> Result<Option<TimeRecord>, TrackerError> {
+        let _lock = self.lock.acquire()?;
+        if !self.lockfile.exists() {
+            self.start_at(task, at)?;
+            return Ok(None);
+        }
+
+        let record = Session::from(read_lockfile(&self.lockfile)?).finish(at.0.into())?;
+
+        let mut db = load_database(&self.database)?;
+        db.push(record.clone());
+        save_database(&self.database, &db)?;
+
+        write_session(&self.lockfile, Some(Session::new(at, task)))?;
+
+        Ok(Some(record))
+    }
+
+    fn restore_session(&mut self, session: Option<Session>) -> Result<(), TrackerError> {
+        let _lock = self.lock.acquire()?;
+        write_session(&self.lockfile, session)
     }
 
-    fn has_records(&self) -> bool {
-        let db = load_database(&self.database).unwrap();
-        !db.records.is_empty()
+    fn has_records(&self) -> Result<bool, TrackerError> {
+        let _lock = self.lock.acquire()?;
+        let db = load_database(&self.database)?;
+        Ok(!db.records.is_empty())
     }
 
     fn records(&self) -> Result<impl Iterator<Item = TimeRecord>, TrackerError> {
+        let _lock = self.lock.acquire()?;
         let db = load_database(&self.database)?;
         Ok(db.records.into_iter())
     }
+
+    fn add(&mut self, record: TimeRecord) -> Result<Vec<TimeRecord>, TrackerError> {
+        record.validate()?;
+
+        let _lock = self.lock.acquire()?;
+        let mut db = load_database(&self.database)?;
+        if db.position(record.id).is_some() {
+            return Err(Report::new(TrackerError))
+                .attach_printable(format!("record {} already exists", record.id));
+        }
+
+        let overlaps = db
+            .records
+            .iter()
+            .filter(|existing| existing.overlaps(&record))
+            .cloned()
+            .collect();
+
+        db.push(record);
+        save_database(&self.database, &db)?;
+
+        Ok(overlaps)
+    }
+
+    fn import<I>(&mut self, records: I) -> Result<usize, TrackerError>
+    where
+        I: IntoIterator<Item = TimeRecord>,
+    {
+        let _lock = self.lock.acquire()?;
+        let mut db = load_database(&self.database)?;
+        let mut ids = db.records.iter().map(|record| record.id).collect::<HashSet<_>>();
+
+        let count = db.records.len();
+        for record in records {
+            record.validate()?;
+            if !ids.insert(record.id) {
+                return Err(Report::new(TrackerError))
+                    .attach_printable(format!("record {} already exists", record.id));
+            }
+            db.push(record);
+        }
+        save_database(&self.database, &db)?;
+
+        Ok(db.records.len() - count)
+    }
+
+    fn clear(&mut self) -> Result<(), TrackerError> {
+        let _lock = self.lock.acquire()?;
+        save_database(&self.database, &FlatFileDatabase::default())
+    }
+
+    fn replace(&mut self, record: TimeRecord) -> Result<TimeRecord, TrackerError> {
+        record.validate()?;
+
+        let _lock = self.lock.acquire()?;
+        let mut db = load_database(&self.database)?;
+        let index = db.find(record.id)?;
+        let previous = std::mem::replace(&mut db.records[index], record);
+        save_database(&self.database, &db)?;
+
+        Ok(previous)
+    }
+
+    fn delete(&mut self, id: RecordId) -> Result<TimeRecord, TrackerError> {
+        let _lock = self.lock.acquire()?;
+        let mut db = load_database(&self.database)?;
+        let index = db.find(id)?;
+        let deleted = db.records.remove(index);
+        save_database(&self.database, &db)?;
+
+        Ok(deleted)
+    }
 }
 
+/// Atomically replaces the database file, see [`replace_file`]. The
+/// previous database is kept as a `.bak` file next to it.
 fn save_database<P>(path: P, db: &FlatFileDatabase) -> Result<(), TrackerError>
 where
-    P: AsRef<std::path::Path>,
+    P: AsRef<Path>,
 {
+    let path = path.as_ref();
     let db = serde_json::to_string(&db)
         .change_context(TrackerError)
         .attach_printable("failed to serialize database")?;
 
-    OpenOptions::new()
+    if path.exists() {
+        rotate_backup(path)?;
+    }
+
+    replace_file(path, db.as_bytes()).attach_printable("unable to write database")
+}
+
+/// Atomically replaces the contents of `path`.
+///
+/// The new contents are written to a temporary file and synced to disk
+/// before being renamed over the file, so the file always exists and is
+/// never left half written.
+pub(super) fn replace_file(path: &Path, data: &[u8]) -> Result<(), TrackerError> {
+    let temp = with_suffix(path, ".tmp");
+
+    let mut file = OpenOptions::new()
         .write(true)
         .create(true)
-        .truncate(false)
-        .open(path.as_ref())
+        .truncate(true)
+        .open(&temp)
         .change_context(TrackerError)
-        .attach_printable("unable to open database when writing")?
-        .write_all(&db.as_bytes())
+        .attach_printable_lazy(|| format!("unable to open temporary file {}", temp.display()))?;
+
+    file.write_all(data)
+        .and_then(|()| file.sync_all())
+        .change_context(TrackerError)
+        .attach_printable_lazy(|| format!("unable to write temporary file {}", temp.display()))?;
+
+    std::fs::rename(&temp, path)
         .change_context(TrackerError)
-        .attach_printable("unable to write database when writing")?;
+        .attach_printable_lazy(|| format!("unable to replace {}", path.display()))?;
+
+    sync_parent_dir(path);
 
     Ok(())
 }
 
+/// Replaces the `.bak` copy with the current database. The database is
+/// hard-linked where possible, and copied otherwise.
+fn rotate_backup(database: &Path) -> Result<(), TrackerError> {
+    let backup = backup_path(database);
+    match std::fs::remove_file(&backup) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::NotFound => {}
+        Err(e) => {
+            return Err(e)
+                .change_context(TrackerError)
+                .attach_printable("unable to remove old database backup when writing")
+        }
+    }
+
+    std::fs::hard_link(database, &backup)
+        .or_else(|_| std::fs::copy(database, &backup).map(|_| ()))
+        .change_context(TrackerError)
+        .attach_printable("unable to rotate database backup when writing")
+}
+
+/// Loads the database, falling back to the `.bak` copy when the database is
+/// unreadable, see [`restore_backup`]. A database of an older format version
+/// is migrated and saved, see [`upgrade_database`].
 fn load_database<P>(database: P) -> Result<FlatFileDatabase, TrackerError>
 where
-    P: AsRef<std::path::Path>,
+    P: AsRef<Path>,
 {
-    let mut db_buf = String::default();
-    OpenOptions::new()
-        .read(true)
-        .write(true)
-        .create(true)
-        .truncate(false)
-        .open(database.as_ref())
+    let database = database.as_ref();
+    let (db, version, from_backup) = recover_database(database)?;
+
+    if from_backup {
+        restore_backup(database)?;
+    }
+    if version < FORMAT_VERSION {
+        upgrade_database(database, &db, version)?;
+    }
+
+    Ok(db)
+}
+
+/// Saves a database that was migrated from `version`, after copying the file
+/// as it was to a backup that is kept until it is deleted by hand.
+fn upgrade_database(database: &Path, db: &FlatFileDatabase, version: u32) -> Result<(), TrackerError> {
+    let backup = migration_backup_path(database, version);
+    if database.exists() {
+        std::fs::copy(database, &backup)
+            .change_context(TrackerError)
+            .attach_printable_lazy(|| format!("unable to back up database to {} before migrating", backup.display()))?;
+    }
+
+    save_database(database, db).attach_printable("unable to save migrated database")?;
+    tracing::info!(
+        database = %database.display(),
+        backup = %backup.display(),
+        from = version,
+        to = FORMAT_VERSION,
+        "migrated database"
+    );
+
+    Ok(())
+}
+
+/// Reads the database or its `.bak` copy, the format version it had before
+/// it was migrated, and whether it was read from the backup. A missing
+/// database is empty, even if a backup is left, since it was deleted on
+/// purpose.
+fn recover_database(database: &Path) -> Result<(FlatFileDatabase, u32, bool), TrackerError> {
+    let backup = backup_path(database);
+
+    let error = match read_database(database) {
+        Ok(Some((db, version))) => return Ok((db, version, false)),
+        Ok(None) => return Ok((FlatFileDatabase::default(), FORMAT_VERSION, false)),
+        Err(e) if !backup.exists() => return Err(e),
+        Err(e) => e,
+    };
+
+    match read_database(&backup) {
+        Ok(Some((db, version))) => {
+            tracing::warn!(
+                database = %database.display(),
+                backup = %backup.display(),
+                records = db.records.len(),
+                "database is missing or unreadable, recovered records from backup"
+            );
+            Ok((db, version, true))
+        }
+        Ok(None) => Ok((FlatFileDatabase::default(), FORMAT_VERSION, false)),
+        Err(backup_error) => {
+            let mut report = backup_error.attach_printable("unable to recover database from backup");
+            report.extend_one(error);
+            Err(report)
+        }
+    }
+}
+
+/// Puts the `.bak` copy back in place of an unreadable database, so that the
+/// next save does not rotate the unreadable database over the backup. The
+/// unreadable database is kept as a `.corrupt` file next to it.
+fn restore_backup(database: &Path) -> Result<(), TrackerError> {
+    let corrupt = with_suffix(database, ".corrupt");
+    std::fs::copy(database, &corrupt)
         .change_context(TrackerError)
-        .attach_printable("unable to open database when reading")?
-        .read_to_string(&mut db_buf)
+        .attach_printable_lazy(|| format!("unable to keep unreadable database as {}", corrupt.display()))?;
+
+    let backup = std::fs::read(backup_path(database))
         .change_context(TrackerError)
-        .attach_printable("unable to read database when reading")?;
+        .attach_printable("unable to read database backup when restoring it")?;
+    replace_file(database, &backup).attach_printable("unable to restore database from backup")?;
 
-    if db_buf.is_empty() {
-        return Ok(FlatFileDatabase::default());
-    } else {
-        Ok(serde_json::from_str(&db_buf)
+    tracing::warn!(
+        database = %database.display(),
+        corrupt = %corrupt.display(),
+        "restored database from backup, the unreadable database was kept"
+    );
+
+    Ok(())
+}
+
+/// Reads and deserializes a database file, migrating it to the current format
+/// version in memory. Returns `None` if the file does not exist, and the
+/// version the file has otherwise.
+fn read_database(path: &Path) -> Result<Option<(FlatFileDatabase, u32)>, TrackerError> {
+    let mut db_buf = String::default();
+    match OpenOptions::new().read(true).open(path) {
+        Ok(mut file) => file
+            .read_to_string(&mut db_buf)
             .change_context(TrackerError)
-            .attach_printable("unable to deserialize database data when reading")?)
+            .attach_printable("unable to read database when reading")?,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => {
+            return Err(e)
+                .change_context(TrackerError)
+                .attach_printable("unable to open database when reading")
+        }
+    };
+
+    if db_buf.is_empty() {
+        return Ok(Some((FlatFileDatabase::default(), FORMAT_VERSION)));
+    }
+
+    parse_database(&db_buf)
+        .map(Some)
+        .attach_printable_lazy(|| format!("database: {}", path.display()))
+}
+
+/// Parses a database of any format version, see [`read_database`].
+fn parse_database(data: &str) -> Result<(FlatFileDatabase, u32), TrackerError> {
+    let mut db = serde_json::from_str(data)
+        .change_context(TrackerError)
+        .attach_printable("unable to parse database data when reading")?;
+    let version = migration::migrate(&mut db)?;
+    let db = serde_json::from_value(db)
+        .change_context(TrackerError)
+        .attach_printable("unable to deserialize database data when reading")?;
+
+    Ok((db, version))
+}
+
+fn migration_backup_path(database: &Path, version: u32) -> PathBuf {
+    with_suffix(database, &format!(".v{version}.bak"))
+}
+
+fn backup_path(database: &Path) -> PathBuf {
+    with_suffix(database, ".bak")
+}
+
+pub(super) fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+/// Makes a rename durable by syncing the containing directory. This is only
+/// possible on unix, and failures are not fatal since the data itself is
+/// already on disk.
+pub(super) fn sync_parent_dir(path: &Path) {
+    #[cfg(unix)]
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        if let Ok(dir) = std::fs::File::open(dir) {
+            let _ = dir.sync_all();
+        }
     }
+    #[cfg(not(unix))]
+    let _ = path;
 }
 
 fn read_lockfile<P>(lockfile: P) -> Result<LockfileData, TrackerError>
@@ -176,97 +553,352 @@ where
         .attach_printable("unable to deserialize lockfile data when reading")
 }
 
+/// The session in the lockfile, `None` if the tracker is not running.
+pub(super) fn read_session(lockfile: &Path) -> Result<Option<Session>, TrackerError> {
+    if !lockfile.exists() {
+        return Ok(None);
+    }
+
+    Ok(Some(read_lockfile(lockfile)?.into()))
+}
+
+/// Creates the lockfile for a new session, unless a session is running.
+pub(super) fn start_session(lockfile: &Path, session: Session) -> Result<StartupStatus, TrackerError> {
+    if lockfile.exists() {
+        return Ok(StartupStatus::Running);
+    }
+
+    let lockfile_data = serde_json::to_string(&LockfileData::from(session))
+        .change_context(TrackerError)
+        .attach_printable("unable to serialize lockfile data when starting tracker")?;
+
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(lockfile)
+        .change_context(TrackerError)
+        .attach_printable("unable to create new lockfile when starting tracker")?
+        .write_all(lockfile_data.as_bytes())
+        .change_context(TrackerError)
+        .attach_printable("unable to write lockfile data when starting tracker")?;
+
+    Ok(StartupStatus::Started)
+}
+
+/// Replaces the session in the lockfile. `None` removes the lockfile.
+pub(super) fn write_session(lockfile: &Path, session: Option<Session>) -> Result<(), TrackerError> {
+    let Some(session) = session else {
+        return match std::fs::remove_file(lockfile) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e)
+                .change_context(TrackerError)
+                .attach_printable("unable to remove lockfile when restoring session"),
+            _ => Ok(()),
+        };
+    };
+
+    let data = serde_json::to_string(&LockfileData::from(session))
+        .change_context(TrackerError)
+        .attach_printable("unable to serialize lockfile data when restoring session")?;
+
+    std::fs::write(lockfile, data)
+        .change_context(TrackerError)
+        .attach_printable("unable to write lockfile when restoring session")
+}
+
 #[cfg(test)]
 mod tests {
-    use assert_fs::{fixture::ChildPath, prelude::PathChild, TempDir};
+    use assert_fs::{prelude::PathChild, TempDir};
 
     use super::*;
+    use crate::feature::tracker::{lock::FileLock, tlib::tracker_tests};
+
+    fn new_tracker(temp: &TempDir) -> FlatFileTracker {
+        FlatFileTracker::new(temp.child("db.json").to_path_buf(), temp.child("lockfile").to_path_buf())
+    }
+
+    tracker_tests!(new_tracker);
+
+    fn record() -> TimeRecord {
+        TimeRecord {
+            id: RecordId::new(),
+            start: StartTime::now(),
+            end: EndTime::now(),
+            pauses: Vec::new(),
+            task: Task::default(),
+        }
+    }
+
+    fn database_with(count: usize) -> FlatFileDatabase {
+        let mut db = FlatFileDatabase::default();
+        for _ in 0..count {
+            db.push(record());
+        }
+        db
+    }
 
-    fn tracking_paths() -> (TempDir, ChildPath, ChildPath) {
+    #[test]
+    fn saving_a_smaller_database_leaves_no_trailing_data() {
         let temp = TempDir::new().unwrap();
-        let db = temp.child("db.json");
-        let lockfile = temp.child("lockfile");
+        let path = temp.child("db.json");
+
+        // Given a database with several records
+        save_database(&path, &database_with(3)).unwrap();
+
+        // When a smaller database is saved over it
+        save_database(&path, &database_with(1)).unwrap();
 
-        (temp, db, lockfile)
+        // Then the database is readable and only has the new records
+        assert_eq!(load_database(&path).unwrap().records.len(), 1);
     }
 
-    fn new_tracker(db: &ChildPath, lockfile: &ChildPath) -> FlatFileTracker {
-        FlatFileTracker::new(db.to_path_buf(), lockfile.to_path_buf())
+    #[test]
+    fn saving_keeps_previous_database_as_backup() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.child("db.json");
+
+        save_database(&path, &database_with(2)).unwrap();
+        save_database(&path, &database_with(3)).unwrap();
+
+        let (backup, _) = read_database(&backup_path(&path)).unwrap().unwrap();
+        assert_eq!(backup.records.len(), 2);
+        assert!(!with_suffix(&path, ".tmp").exists());
     }
 
     #[test]
-    fn starts_tracking_with_default_tracker() {
-        let (_temp, db, lockfile) = tracking_paths();
-        // Given a new tracker
-        let mut tracker = new_tracker(&db, &lockfile);
+    fn corrupted_database_is_recovered_from_backup() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.child("db.json");
+
+        // Given a database with a backup
+        save_database(&path, &database_with(2)).unwrap();
+        save_database(&path, &database_with(3)).unwrap();
 
-        // When the tracker is started
-        tracker.start().unwrap();
+        // When the database is corrupted
+        std::fs::write(&path, "{\"records\": [").unwrap();
 
-        // Then the tracker is running
-        assert!(tracker.is_running());
+        // Then the backup is loaded
+        assert_eq!(load_database(&path).unwrap().records.len(), 2);
     }
 
     #[test]
-    fn is_running_returns_false_after_stopping_tracker() {
-        let (_temp, db, lockfile) = tracking_paths();
+    fn recovered_database_does_not_replace_the_backup() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.child("db.json");
+
+        // Given a corrupted database that was recovered from its backup
+        save_database(&path, &database_with(2)).unwrap();
+        save_database(&path, &database_with(3)).unwrap();
+        std::fs::write(&path, "{\"records\": [").unwrap();
+        let mut db = load_database(&path).unwrap();
+
+        // When the recovered database is saved
+        db.push(record());
+        save_database(&path, &db).unwrap();
+
+        // Then the backup still holds the recovered records
+        let (backup, _) = read_database(&backup_path(&path)).unwrap().unwrap();
+        assert_eq!(backup.records.len(), 2);
+        assert_eq!(load_database(&path).unwrap().records.len(), 3);
+        assert_eq!(
+            std::fs::read_to_string(with_suffix(&path, ".corrupt")).unwrap(),
+            "{\"records\": ["
+        );
+    }
 
-        // Given a new tracker
-        let mut tracker = new_tracker(&db, &lockfile);
-        // When the tracker is started
-        tracker.start().unwrap();
-        tracker.stop().unwrap();
+    #[test]
+    fn deleted_database_is_not_recovered_from_backup() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.child("db.json");
 
-        // Then the tracker is running
-        assert!(!tracker.is_running());
+        // Given a database with a backup
+        save_database(&path, &database_with(2)).unwrap();
+        save_database(&path, &database_with(3)).unwrap();
+
+        // When the database is deleted
+        std::fs::remove_file(&path).unwrap();
+
+        // Then it is empty
+        assert!(load_database(&path).unwrap().records.is_empty());
+    }
+
+    #[test]
+    fn backup_is_a_separate_file() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.child("db.json");
+
+        save_database(&path, &database_with(2)).unwrap();
+        save_database(&path, &database_with(3)).unwrap();
+        save_database(&path, &database_with(1)).unwrap();
+
+        // The backup holds the previous database, not a link to the current one
+        let (backup, _) = read_database(&backup_path(&path)).unwrap().unwrap();
+        assert_eq!(backup.records.len(), 3);
+        assert_eq!(load_database(&path).unwrap().records.len(), 1);
+    }
+
+    #[test]
+    fn reads_records_without_task_fields() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.child("db.json");
+
+        // Given a database written before records had a task
+        std::fs::write(
+            &path,
+            r#"{"records":[{"start":"2024-11-23T18:02:11.797947Z","end":"2024-11-23T19:02:11.797947Z"}]}"#,
+        )
+        .unwrap();
+
+        // Then the records are read with an empty task
+        let db = load_database(&path).unwrap();
+        assert_eq!(db.records[0].task, Task::default());
+    }
+
+    #[test]
+    fn records_without_ids_get_persistent_ids() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.child("db.json");
+
+        // Given a database written before records had an ID
+        std::fs::write(
+            &path,
+            r#"{"records":[{"start":"2024-11-23T18:02:11.797947Z","end":"2024-11-23T19:02:11.797947Z"}]}"#,
+        )
+        .unwrap();
+
+        // Then the record gets an ID that is kept on the next load
+        let id = load_database(&path).unwrap().records[0].id;
+        assert!(!id.is_nil());
+        assert_eq!(load_database(&path).unwrap().records[0].id, id);
+    }
+
+    /// Databases as written before each format version change, and by the
+    /// current version.
+    const FIXTURES: &[(&str, u32)] = &[
+        ("v0-start-end.json", 0),
+        ("v0-task.json", 0),
+        ("v0-id-pauses.json", 0),
+        ("v1.json", 1),
+    ];
+
+    fn fixture(name: &str) -> String {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/flatfile").join(name);
+        std::fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn databases_of_every_version_are_migrated_after_a_backup() {
+        for (name, version) in FIXTURES {
+            let temp = TempDir::new().unwrap();
+            let path = temp.child("db.json");
+            std::fs::write(&path, fixture(name)).unwrap();
+
+            let db = load_database(&path).unwrap();
+
+            assert_eq!(db.records.len(), 2, "{name}");
+            assert!(db.records.iter().all(|record| !record.id.is_nil()), "{name}");
+            let saved: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+            assert_eq!(saved["version"], FORMAT_VERSION, "{name}");
+
+            let backup = migration_backup_path(&path, *version);
+            if *version < FORMAT_VERSION {
+                assert_eq!(std::fs::read_to_string(backup).unwrap(), fixture(name), "{name}");
+            } else {
+                assert!(!backup.exists(), "{name}");
+            }
+        }
+    }
+
+    #[test]
+    fn migrated_records_keep_their_data() {
+        let (v0, _) = parse_database(&fixture("v0-id-pauses.json")).unwrap();
+        let (v1, _) = parse_database(&fixture("v1.json")).unwrap();
+
+        assert_eq!(v0.records, v1.records);
+        assert_eq!(v1.records[0].task.project.as_deref(), Some("website"));
+        assert_eq!(v1.records[1].pauses.len(), 1);
+    }
+
+    #[test]
+    fn dry_run_leaves_the_database_unchanged() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.child("db.json");
+        std::fs::write(&path, fixture("v0-task.json")).unwrap();
+        let mut tracker = new_tracker(&temp);
+
+        let report = tracker.migrate(true).unwrap();
+
+        assert_eq!((report.from, report.to, report.migrations.len()), (0, FORMAT_VERSION, 1));
+        assert_eq!(report.backup, Some(migration_backup_path(&path, 0)));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), fixture("v0-task.json"));
+        assert!(!migration_backup_path(&path, 0).exists());
+
+        tracker.migrate(false).unwrap();
+        assert!(tracker.migrate(true).unwrap().migrations.is_empty());
     }
 
     #[test]
-    fn time_record_created_when_tracking_stops() {
-        let (_temp, db, lockfile) = tracking_paths();
+    fn database_of_a_newer_version_is_not_read() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.child("db.json");
+        let newer = format!(r#"{{"version":{},"records":[]}}"#, FORMAT_VERSION + 1);
+        std::fs::write(&path, &newer).unwrap();
 
-        // Given a new tracker
-        let mut tracker = new_tracker(&db, &lockfile);
-        // When the tracker is started
-        tracker.start().unwrap();
+        assert!(load_database(&path).is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), newer);
+    }
 
-        std::thread::sleep(std::time::Duration::from_secs(1));
+    #[test]
+    fn reads_lockfile_without_task_fields() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.child("lockfile");
 
-        tracker.stop().unwrap();
+        std::fs::write(&path, r#"{"start_time":"2024-11-23T18:02:11.797947Z"}"#).unwrap();
 
-        // Then a record is saved
-        assert!(tracker.records().unwrap().next().is_some());
+        assert_eq!(read_lockfile(&path).unwrap().task, Task::default());
     }
 
     #[test]
-    fn initial_start_returns_started_state() {
-        let (_temp, db, lockfile) = tracking_paths();
+    fn waiting_for_a_locked_database_times_out() {
+        let temp = TempDir::new().unwrap();
+        let _held = FileLock::acquire(&temp.child("db.json.lock"), Duration::ZERO).unwrap();
 
-        // Given a new tracker
-        let mut tracker = new_tracker(&db, &lockfile);
-        // When the tracker is started
-        let started = tracker.start().unwrap();
+        let mut tracker = new_tracker(&temp).with_lock_timeout(Duration::from_millis(20));
 
-        std::thread::sleep(std::time::Duration::from_secs(1));
+        let error = tracker.start(Task::default()).unwrap_err();
+        assert!(format!("{error:?}").contains("timed out"));
+        assert!(!tracker.is_running());
 
-        // Then the "already running" state is returned
-        assert_eq!(started, StartupStatus::Started);
+        // Queries time out with the same error instead of panicking
+        let error = tracker.has_records().unwrap_err();
+        assert!(format!("{error:?}").contains("timed out"));
     }
 
     #[test]
-    fn multiple_starts_returns_already_running_state() {
-        let (_temp, db, lockfile) = tracking_paths();
+    fn lockfile_records_where_the_session_was_started() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.child("lockfile");
 
-        // Given a new tracker
-        let mut tracker = new_tracker(&db, &lockfile);
-        // When the tracker is started
-        tracker.start().unwrap();
+        // Lockfiles written before the origin was recorded have none
+        std::fs::write(&path, r#"{"start_time":"2024-11-23T18:02:11.797947Z","project":"website"}"#).unwrap();
+        assert_eq!(read_lockfile(&path).unwrap().origin, None);
 
-        std::thread::sleep(std::time::Duration::from_secs(1));
+        std::fs::remove_file(&path).unwrap();
+        let mut tracker = FlatFileTracker::new(temp.child("db.json").to_path_buf(), path.to_path_buf());
+        tracker.start(Task::default()).unwrap();
+
+        let data: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        let origin = Origin::current();
+        assert_eq!(data["hostname"], origin.hostname);
+        assert_eq!(data["pid"], origin.pid);
+    }
+
+    #[test]
+    fn corrupted_database_without_backup_is_an_error() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.child("db.json");
 
-        let started = tracker.start().unwrap();
+        std::fs::write(&path, "{\"records\": [").unwrap();
 
-        // Then the "already running" state is returned
-        assert_eq!(started, StartupStatus::Running);
+        assert!(load_database(&path).is_err());
     }
 }
@@ -0,0 +1,773 @@
+// journal of changes made through the tracker
+// 1 file, next to the database:
+// - "done": changes that can be undone, oldest first
+// - "undone": changes that can be redone, most recently undone last
+// ("<journal>.lock" is locked while a process changes the journal)
+
+use std::{
+    fs::OpenOptions,
+    io::{ErrorKind, Read},
+    path::PathBuf,
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use error_stack::{Report, Result, ResultExt};
+use serde::{Deserialize, Serialize};
+
+use crate::error::Suggestion;
+
+use super::{
+    flatfile::{replace_file, with_suffix},
+    lock::{FileLock, LOCK_TIMEOUT},
+    EndTime, RecordId, ReportConfig, ReportTimespan, Reporter, ReporterError, Session, StartTime,
+    StartupStatus, StorageLock, Task, TimeRecord, Tracker, TrackerError,
+};
+
+/// Number of changes kept in the journal. Older changes can not be undone.
+pub const HISTORY_LIMIT: usize = 100;
+
+/// A change made through the tracker, holding what is needed to apply it
+/// again and to revert it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum Change {
+    Start { session: Session },
+    Stop { session: Session, record: TimeRecord },
+    Switch { before: Session, record: TimeRecord, after: Session },
+    Cancel { session: Session },
+    Pause { before: Session, after: Session },
+    Resume { before: Session, after: Session },
+    Add { record: TimeRecord },
+    Edit { before: TimeRecord, after: TimeRecord },
+    Delete { record: TimeRecord },
+}
+
+impl Change {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Change::Start { .. } => "start",
+            Change::Stop { .. } => "stop",
+            Change::Switch { .. } => "switch",
+            Change::Cancel { .. } => "cancel",
+            Change::Pause { .. } => "pause",
+            Change::Resume { .. } => "resume",
+            Change::Add { .. } => "add",
+            Change::Edit { .. } => "edit",
+            Change::Delete { .. } => "delete",
+        }
+    }
+
+    /// The record the change is about, i.e. the record after the change or
+    /// the deleted record. `None` for changes to the running session.
+    pub fn record(&self) -> Option<&TimeRecord> {
+        match self {
+            Change::Start { .. }
+            | Change::Cancel { .. }
+            | Change::Pause { .. }
+            | Change::Resume { .. } => None,
+            Change::Stop { record, .. }
+            | Change::Switch { record, .. }
+            | Change::Add { record }
+            | Change::Delete { record } => Some(record),
+            Change::Edit { after, .. } => Some(after),
+        }
+    }
+
+    /// When the session or record the change is about starts and ends. The
+    /// end is `None` for changes to the running session.
+    pub fn span(&self) -> (DateTime<Utc>, Option<DateTime<Utc>>) {
+        match self {
+            Change::Start { session }
+            | Change::Cancel { session }
+            | Change::Pause { after: session, .. }
+            | Change::Resume { after: session, .. } => (session.start.instant(), None),
+            Change::Stop { record, .. }
+            | Change::Switch { record, .. }
+            | Change::Add { record }
+            | Change::Edit { after: record, .. }
+            | Change::Delete { record } => (record.start.instant(), Some(record.end.instant())),
+        }
+    }
+
+    /// Makes the change on `tracker`.
+    pub fn apply<T>(&self, tracker: &mut T) -> Result<(), TrackerError>
+    where
+        T: Tracker,
+    {
+        match self {
+            Change::Start { session } => {
+                ensure_stopped(tracker)?;
+                tracker.restore_session(Some(session.clone()))
+            }
+            Change::Stop { record, .. } => {
+                tracker.add(record.clone())?;
+                tracker.restore_session(None)
+            }
+            Change::Switch { record, after, .. } => {
+                tracker.add(record.clone())?;
+                tracker.restore_session(Some(after.clone()))
+            }
+            Change::Cancel { .. } => tracker.cancel().map(|_| ()),
+            Change::Pause { after, .. } | Change::Resume { after, .. } => {
+                tracker.restore_session(Some(after.clone()))
+            }
+            Change::Add { record } => tracker.add(record.clone()).map(|_| ()),
+            Change::Edit { after, .. } => tracker.replace(after.clone()).map(|_| ()),
+            Change::Delete { record } => tracker.delete(record.id).map(|_| ()),
+        }
+    }
+
+    /// Undoes the change on `tracker`.
+    pub fn revert<T>(&self, tracker: &mut T) -> Result<(), TrackerError>
+    where
+        T: Tracker,
+    {
+        match self {
+            Change::Start { .. } => tracker.restore_session(None),
+            Change::Stop { session, record } => {
+                ensure_stopped(tracker)?;
+                tracker.delete(record.id)?;
+                tracker.restore_session(Some(session.clone()))
+            }
+            Change::Switch { before, record, .. } => {
+                tracker.delete(record.id)?;
+                tracker.restore_session(Some(before.clone()))
+            }
+            Change::Cancel { session } => {
+                ensure_stopped(tracker)?;
+                tracker.restore_session(Some(session.clone()))
+            }
+            Change::Pause { before, .. } | Change::Resume { before, .. } => {
+                tracker.restore_session(Some(before.clone()))
+            }
+            Change::Add { record } => tracker.delete(record.id).map(|_| ()),
+            Change::Edit { before, .. } => tracker.replace(before.clone()).map(|_| ()),
+            Change::Delete { record } => tracker.add(record.clone()).map(|_| ()),
+        }
+    }
+}
+
+/// Restoring a session must not throw away the one that is running.
+fn ensure_stopped<T>(tracker: &T) -> Result<(), TrackerError>
+where
+    T: Tracker,
+{
+    if tracker.is_running() {
+        return Err(Report::new(TrackerError))
+            .attach_printable("another session is running")
+            .attach(Suggestion("stop the running session first"));
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub change: Change,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct History {
+    /// Changes that can be undone, oldest first
+    pub done: Vec<JournalEntry>,
+    /// Changes that can be redone, most recently undone last
+    pub undone: Vec<JournalEntry>,
+}
+
+impl History {
+    /// Every change, newest first, and whether it was undone.
+    pub fn newest_first(&self) -> impl Iterator<Item = (&JournalEntry, bool)> {
+        let undone = self.undone.iter().map(|entry| (entry, true));
+        undone.chain(self.done.iter().rev().map(|entry| (entry, false)))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.done.is_empty() && self.undone.is_empty()
+    }
+}
+
+/// A tracker that journals every change made through it, so that changes
+/// can be undone and redone.
+pub struct Journaled<T> {
+    tracker: T,
+    journal: PathBuf,
+}
+
+impl<T> Journaled<T> {
+    pub fn new<J>(tracker: T, journal: J) -> Self
+    where
+        J: Into<PathBuf>,
+    {
+        let journal = journal.into();
+
+        Journaled { tracker, journal }
+    }
+
+    /// The wrapped tracker, for changes that are not journaled such as
+    /// migrating its storage.
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.tracker
+    }
+
+    pub fn history(&self) -> Result<History, TrackerError> {
+        let mut buf = String::new();
+        match OpenOptions::new().read(true).open(&self.journal) {
+            Ok(mut file) => file
+                .read_to_string(&mut buf)
+                .change_context(TrackerError)
+                .attach_printable("unable to read journal")?,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(History::default()),
+            Err(e) => {
+                return Err(e)
+                    .change_context(TrackerError)
+                    .attach_printable("unable to open journal when reading")
+            }
+        };
+
+        if buf.trim().is_empty() {
+            return Ok(History::default());
+        }
+
+        serde_json::from_str(&buf)
+            .change_context(TrackerError)
+            .attach_printable("unable to deserialize journal")
+            .attach_printable_lazy(|| format!("journal: {}", self.journal.display()))
+    }
+
+    /// Locks the journal against changes by other processes.
+    fn lock(&self) -> Result<FileLock, TrackerError> {
+        FileLock::acquire(&with_suffix(&self.journal, ".lock"), LOCK_TIMEOUT)
+    }
+
+    /// Writes the journal through a temporary file, so that it is never left
+    /// half written.
+    fn save(&self, history: &History) -> Result<(), TrackerError> {
+        let data = serde_json::to_string(history)
+            .change_context(TrackerError)
+            .attach_printable("unable to serialize journal")?;
+
+        replace_file(&self.journal, data.as_bytes()).attach_printable("unable to write journal")
+    }
+
+    /// Records a new change while the journal is locked. Changes that were
+    /// undone can no longer be redone.
+    fn record(&self, change: Change) -> Result<(), TrackerError> {
+        let mut history = self.history()?;
+
+        history.undone.clear();
+        history.done.push(JournalEntry {
+            at: Utc::now(),
+            change,
+        });
+        if history.done.len() > HISTORY_LIMIT {
+            history.done.drain(..history.done.len() - HISTORY_LIMIT);
+        }
+
+        self.save(&history)
+    }
+}
+
+impl<T> Journaled<T>
+where
+    T: Tracker,
+{
+    /// Makes a change and journals it while both the tracker and the journal
+    /// are locked, so that changes from other processes are journaled in the
+    /// order they were made. The change is reverted if it cannot be journaled.
+    fn journaled<R, F>(&mut self, f: F) -> Result<R, TrackerError>
+    where
+        F: FnOnce(&mut T) -> Result<(R, Option<Change>), TrackerError>,
+    {
+        let _storage = self.tracker.lock_storage()?;
+        let _journal = self.lock()?;
+
+        let (result, change) = f(&mut self.tracker)?;
+        if let Some(change) = change {
+            if let Err(mut e) = self.record(change.clone()) {
+                if let Err(revert) = change.revert(&mut self.tracker) {
+                    e.extend_one(
+                        revert.attach_printable("unable to revert the change that was not journaled"),
+                    );
+                }
+                return Err(e);
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Reverts the most recent change. Returns `None` if there is nothing to
+    /// undo.
+    pub fn undo(&mut self) -> Result<Option<JournalEntry>, TrackerError> {
+        let _storage = self.tracker.lock_storage()?;
+        let _journal = self.lock()?;
+        let mut history = self.history()?;
+        let Some(entry) = history.done.pop() else {
+            return Ok(None);
+        };
+
+        entry
+            .change
+            .revert(&mut self.tracker)
+            .attach_printable_lazy(|| format!("unable to undo {}", entry.change.name()))?;
+
+        history.undone.push(entry.clone());
+        self.save(&history)?;
+
+        Ok(Some(entry))
+    }
+
+    /// Applies the most recently undone change again. Returns `None` if
+    /// there is nothing to redo.
+    pub fn redo(&mut self) -> Result<Option<JournalEntry>, TrackerError> {
+        let _storage = self.tracker.lock_storage()?;
+        let _journal = self.lock()?;
+        let mut history = self.history()?;
+        let Some(entry) = history.undone.pop() else {
+            return Ok(None);
+        };
+
+        entry
+            .change
+            .apply(&mut self.tracker)
+            .attach_printable_lazy(|| format!("unable to redo {}", entry.change.name()))?;
+
+        history.done.push(entry.clone());
+        self.save(&history)?;
+
+        Ok(Some(entry))
+    }
+}
+
+impl<T> Tracker for Journaled<T>
+where
+    T: Tracker,
+{
+    /// Locks the wrapped tracker only, the journal is locked by every change
+    /// on its own.
+    fn lock_storage(&self) -> Result<StorageLock, TrackerError> {
+        self.tracker.lock_storage()
+    }
+
+    fn start_at(&mut self, task: Task, start: StartTime) -> Result<StartupStatus, TrackerError> {
+        self.journaled(|tracker| {
+            let status = tracker.start_at(task.clone(), start)?;
+            let change = (status == StartupStatus::Started).then(|| Change::Start {
+                session: Session::new(start, task),
+            });
+
+            Ok((status, change))
+        })
+    }
+
+    fn is_running(&self) -> bool {
+        self.tracker.is_running()
+    }
+
+    fn session(&self) -> Result<Option<Session>, TrackerError> {
+        self.tracker.session()
+    }
+
+    fn stop_at(&mut self, end: EndTime) -> Result<TimeRecord, TrackerError> {
+        self.journaled(|tracker| {
+            let session = tracker.session()?;
+            let record = tracker.stop_at(end)?;
+            let change = session.map(|session| Change::Stop {
+                session,
+                record: record.clone(),
+            });
+
+            Ok((record, change))
+        })
+    }
+
+    fn switch_at(&mut self, task: Task, at: StartTime) -> Result<Option<TimeRecord>, TrackerError> {
+        self.journaled(|tracker| {
+            let before = tracker.session()?;
+            let record = tracker.switch_at(task.clone(), at)?;
+            let after = Session::new(at, task);
+            let change = match (before, &record) {
+                (Some(before), Some(record)) => Change::Switch {
+                    before,
+                    record: record.clone(),
+                    after,
+                },
+                _ => Change::Start { session: after },
+            };
+
+            Ok((record, Some(change)))
+        })
+    }
+
+    fn restore_session(&mut self, session: Option<Session>) -> Result<(), TrackerError> {
+        self.tracker.restore_session(session)
+    }
+
+    fn pause_at(&mut self, at: DateTime<Utc>) -> Result<Session, TrackerError> {
+        self.journaled(|tracker| {
+            let before = tracker.session()?;
+            let after = tracker.pause_at(at)?;
+            let change = before.map(|before| Change::Pause {
+                before,
+                after: after.clone(),
+            });
+
+            Ok((after, change))
+        })
+    }
+
+    fn resume_at(&mut self, at: DateTime<Utc>) -> Result<Session, TrackerError> {
+        self.journaled(|tracker| {
+            let before = tracker.session()?;
+            let after = tracker.resume_at(at)?;
+            let change = before.map(|before| Change::Resume {
+                before,
+                after: after.clone(),
+            });
+
+            Ok((after, change))
+        })
+    }
+
+    fn cancel(&mut self) -> Result<Session, TrackerError> {
+        self.journaled(|tracker| {
+            let session = tracker.cancel()?;
+            let change = Change::Cancel {
+                session: session.clone(),
+            };
+
+            Ok((session, Some(change)))
+        })
+    }
+
+    fn has_records(&self) -> Result<bool, TrackerError> {
+        self.tracker.has_records()
+    }
+
+    fn records(&self) -> Result<impl Iterator<Item = TimeRecord>, TrackerError> {
+        self.tracker.records()
+    }
+
+    fn add(&mut self, record: TimeRecord) -> Result<Vec<TimeRecord>, TrackerError> {
+        self.journaled(|tracker| {
+            let overlaps = tracker.add(record.clone())?;
+            Ok((overlaps, Some(Change::Add { record })))
+        })
+    }
+
+    /// Imports are not journaled, a journal entry per record would push
+    /// every other change out of the history.
+    fn import<I>(&mut self, records: I) -> Result<usize, TrackerError>
+    where
+        I: IntoIterator<Item = TimeRecord>,
+    {
+        self.tracker.import(records)
+    }
+
+    /// Not journaled either, like imports.
+    fn clear(&mut self) -> Result<(), TrackerError> {
+        self.tracker.clear()
+    }
+
+    fn replace(&mut self, record: TimeRecord) -> Result<TimeRecord, TrackerError> {
+        self.journaled(|tracker| {
+            let before = tracker.replace(record.clone())?;
+            let change = Change::Edit {
+                before: before.clone(),
+                after: record,
+            };
+
+            Ok((before, Some(change)))
+        })
+    }
+
+    fn delete(&mut self, id: RecordId) -> Result<TimeRecord, TrackerError> {
+        self.journaled(|tracker| {
+            let record = tracker.delete(id)?;
+            let change = Change::Delete {
+                record: record.clone(),
+            };
+
+            Ok((record, Some(change)))
+        })
+    }
+}
+
+impl<T> Reporter for Journaled<T>
+where
+    T: Reporter,
+{
+    fn total_duration(
+        &self,
+        timespan: ReportTimespan,
+        config: &ReportConfig,
+    ) -> Result<Duration, ReporterError> {
+        self.tracker.total_duration(timespan, config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_fs::{prelude::PathChild, TempDir};
+
+    use super::*;
+    use crate::feature::tracker::{MemoryTracker, RecordEdit};
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    fn record(start: &str, end: &str) -> TimeRecord {
+        TimeRecord {
+            id: RecordId::new(),
+            start: utc(start).into(),
+            end: utc(end).into(),
+            pauses: Vec::new(),
+            task: Task::default(),
+        }
+    }
+
+    fn journaled(temp: &TempDir, records: Vec<TimeRecord>) -> Journaled<MemoryTracker> {
+        Journaled::new(MemoryTracker::with_records(records), temp.child("journal").to_path_buf())
+    }
+
+    fn changes(tracker: &Journaled<MemoryTracker>) -> Vec<&'static str> {
+        let history = tracker.history().unwrap();
+        history.done.iter().map(|entry| entry.change.name()).collect()
+    }
+
+    fn records(tracker: &Journaled<MemoryTracker>) -> Vec<TimeRecord> {
+        tracker.records().unwrap().collect()
+    }
+
+    #[test]
+    fn atomically_locks_the_wrapped_tracker() {
+        use crate::feature::tracker::FlatFileTracker;
+
+        let temp = TempDir::new().unwrap();
+        let (db, lockfile) = (temp.child("db.json").to_path_buf(), temp.child("lockfile").to_path_buf());
+        let flatfile = || FlatFileTracker::new(&db, &lockfile);
+        let mut tracker = Journaled::new(flatfile(), temp.child("journal").to_path_buf());
+
+        tracker
+            .atomically(|_| {
+                // Another process waiting for the database gives up
+                let mut other = flatfile().with_lock_timeout(Duration::from_millis(20));
+                assert!(other.start(Task::default()).is_err());
+                Ok(())
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn change_that_cannot_be_journaled_is_reverted() {
+        let temp = TempDir::new().unwrap();
+        let mut tracker = journaled(&temp, Vec::new());
+
+        // A journal that cannot be read
+        std::fs::create_dir(temp.child("journal")).unwrap();
+
+        assert!(tracker.add(record("2026-10-15T09:00:00Z", "2026-10-15T10:00:00Z")).is_err());
+        assert!(tracker.start(Task::default()).is_err());
+        assert!(records(&tracker).is_empty());
+        assert!(!tracker.is_running());
+    }
+
+    #[test]
+    fn clearing_is_not_journaled() {
+        let temp = TempDir::new().unwrap();
+        let mut tracker = journaled(&temp, Vec::new());
+        tracker.add(record("2026-10-15T09:00:00Z", "2026-10-15T10:00:00Z")).unwrap();
+        tracker.add(record("2026-10-15T11:00:00Z", "2026-10-15T12:00:00Z")).unwrap();
+
+        tracker.clear().unwrap();
+
+        assert!(records(&tracker).is_empty());
+        assert_eq!(changes(&tracker), vec!["add", "add"]);
+    }
+
+    #[test]
+    fn every_change_is_journaled() {
+        let temp = TempDir::new().unwrap();
+        let mut tracker = journaled(&temp, Vec::new());
+
+        tracker.start(Task::default()).unwrap();
+        tracker.start(Task::default()).unwrap();
+        let stopped = tracker.stop().unwrap();
+        tracker.add(record("2026-10-15T09:00:00Z", "2026-10-15T10:00:00Z")).unwrap();
+        let edit = RecordEdit {
+            project: Some(Some("website".to_string())),
+            ..Default::default()
+        };
+        tracker.edit(stopped.id, &edit).unwrap();
+        tracker.delete(stopped.id).unwrap();
+
+        // The second start did nothing and is not journaled
+        assert_eq!(changes(&tracker), ["start", "stop", "add", "edit", "delete"]);
+    }
+
+    #[test]
+    fn failed_changes_are_not_journaled() {
+        let temp = TempDir::new().unwrap();
+        let mut tracker = journaled(&temp, Vec::new());
+
+        assert!(tracker.delete(RecordId::new()).is_err());
+        assert!(tracker.add(record("2026-10-15T10:00:00Z", "2026-10-15T09:00:00Z")).is_err());
+        assert!(changes(&tracker).is_empty());
+    }
+
+    #[test]
+    fn undo_and_redo_a_stop() {
+        let temp = TempDir::new().unwrap();
+        let mut tracker = journaled(&temp, Vec::new());
+
+        tracker.start_at(Task::default(), utc("2026-10-15T09:00:00Z").into()).unwrap();
+        let stopped = tracker.stop().unwrap();
+
+        // Undoing the stop brings the session back
+        assert_eq!(tracker.undo().unwrap().unwrap().change.name(), "stop");
+        assert!(tracker.is_running());
+        assert!(records(&tracker).is_empty());
+
+        // Redoing it stores the same record again
+        assert_eq!(tracker.redo().unwrap().unwrap().change.name(), "stop");
+        assert!(!tracker.is_running());
+        assert_eq!(records(&tracker), vec![stopped]);
+    }
+
+    #[test]
+    fn undo_everything_then_redo_everything() {
+        let temp = TempDir::new().unwrap();
+        let original = record("2026-10-15T09:00:00Z", "2026-10-15T10:00:00Z");
+        let mut tracker = journaled(&temp, vec![original.clone()]);
+
+        let edit = RecordEdit {
+            end: Some(utc("2026-10-15T11:00:00Z").into()),
+            ..Default::default()
+        };
+        tracker.edit(original.id, &edit).unwrap();
+        let added = record("2026-10-15T12:00:00Z", "2026-10-15T13:00:00Z");
+        tracker.add(added.clone()).unwrap();
+        tracker.delete(original.id).unwrap();
+        tracker.start(Task::default()).unwrap();
+
+        while tracker.undo().unwrap().is_some() {}
+        assert_eq!(records(&tracker), vec![original]);
+        assert!(!tracker.is_running());
+
+        while tracker.redo().unwrap().is_some() {}
+        assert_eq!(records(&tracker), vec![added]);
+        assert!(tracker.is_running());
+    }
+
+    #[test]
+    fn undo_brings_back_a_cancelled_session() {
+        let temp = TempDir::new().unwrap();
+        let mut tracker = journaled(&temp, Vec::new());
+
+        tracker.start(Task::default()).unwrap();
+        let cancelled = tracker.cancel().unwrap();
+
+        assert_eq!(tracker.undo().unwrap().unwrap().change.name(), "cancel");
+        assert_eq!(tracker.session().unwrap(), Some(cancelled));
+        assert!(records(&tracker).is_empty());
+    }
+
+    #[test]
+    fn undo_and_redo_a_pause() {
+        let temp = TempDir::new().unwrap();
+        let mut tracker = journaled(&temp, Vec::new());
+
+        tracker.start_at(Task::default(), utc("2026-10-15T09:00:00Z").into()).unwrap();
+        tracker.pause_at(utc("2026-10-15T10:00:00Z")).unwrap();
+
+        tracker.undo().unwrap();
+        assert!(!tracker.session().unwrap().unwrap().is_paused());
+
+        tracker.redo().unwrap();
+        assert_eq!(tracker.session().unwrap().unwrap().paused_at, Some(utc("2026-10-15T10:00:00Z")));
+    }
+
+    #[test]
+    fn undo_and_redo_a_switch() {
+        let temp = TempDir::new().unwrap();
+        let mut tracker = journaled(&temp, Vec::new());
+        let website = Task {
+            project: Some("website".to_string()),
+            ..Default::default()
+        };
+
+        tracker.start_at(Task::default(), utc("2026-10-15T09:00:00Z").into()).unwrap();
+        let record = tracker
+            .switch_at(website.clone(), utc("2026-10-15T10:00:00Z").into())
+            .unwrap()
+            .unwrap();
+        assert_eq!(changes(&tracker), ["start", "switch"]);
+
+        // Undo brings back the first session, without the record
+        tracker.undo().unwrap();
+        assert!(records(&tracker).is_empty());
+        assert_eq!(tracker.session().unwrap().unwrap().task, Task::default());
+
+        // Redo closes it again at the same instant
+        tracker.redo().unwrap();
+        assert_eq!(records(&tracker), [record]);
+        assert_eq!(tracker.session().unwrap().unwrap().task, website);
+    }
+
+    #[test]
+    fn new_change_clears_redo() {
+        let temp = TempDir::new().unwrap();
+        let mut tracker = journaled(&temp, Vec::new());
+
+        tracker.add(record("2026-10-15T09:00:00Z", "2026-10-15T10:00:00Z")).unwrap();
+        tracker.undo().unwrap();
+        tracker.add(record("2026-10-15T11:00:00Z", "2026-10-15T12:00:00Z")).unwrap();
+
+        assert_eq!(tracker.redo().unwrap(), None);
+        assert_eq!(records(&tracker).len(), 1);
+    }
+
+    #[test]
+    fn history_is_bounded() {
+        let temp = TempDir::new().unwrap();
+        let mut tracker = journaled(&temp, Vec::new());
+
+        for _ in 0..HISTORY_LIMIT + 5 {
+            tracker.add(record("2026-10-15T09:00:00Z", "2026-10-15T10:00:00Z")).unwrap();
+        }
+
+        assert_eq!(tracker.history().unwrap().done.len(), HISTORY_LIMIT);
+    }
+
+    #[test]
+    fn undoing_a_stop_does_not_replace_a_running_session() {
+        let temp = TempDir::new().unwrap();
+        let mut tracker = journaled(&temp, Vec::new());
+
+        tracker.start(Task::default()).unwrap();
+        tracker.stop().unwrap();
+        tracker.tracker.start(Task::default()).unwrap();
+
+        assert!(tracker.undo().is_err());
+        assert_eq!(records(&tracker).len(), 1);
+    }
+
+    #[test]
+    fn failed_undo_keeps_the_journal() {
+        let temp = TempDir::new().unwrap();
+        let mut tracker = journaled(&temp, Vec::new());
+
+        // Given a journaled add whose record was removed behind the journal's back
+        let added = record("2026-10-15T09:00:00Z", "2026-10-15T10:00:00Z");
+        tracker.add(added.clone()).unwrap();
+        tracker.tracker.delete(added.id).unwrap();
+
+        // Then undoing it fails and the change stays in the journal
+        assert!(tracker.undo().is_err());
+        assert_eq!(changes(&tracker), ["add"]);
+    }
+}
@@ -0,0 +1,611 @@
+// JSON Lines tracker
+// 2 files:
+// - "lockfile": tracker is running, as for the flat file tracker
+// - "records.jsonl": one change per line, oldest first. A "put" line holds a
+//   record and replaces any earlier record with the same ID, a "delete" line
+//   removes one. Stopping the tracker appends a single line, and compaction
+//   rewrites the file with one "put" line per record.
+//   ("records.jsonl.lock" is locked while a process reads or changes the files)
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, ErrorKind, Read, Seek, SeekFrom, Write},
+    path::PathBuf,
+    time::Duration,
+};
+
+use error_stack::{Report, Result, ResultExt};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    flatfile::{read_session, start_session, sync_parent_dir, with_suffix, write_session},
+    lock::{ReentrantLock, LOCK_TIMEOUT},
+    EndTime, RecordId, Reporter, Session, StartTime, StartupStatus, StorageLock, Task, TimeRecord, Tracker, TrackerError,
+};
+
+/// Files with fewer lines are never compacted.
+pub const COMPACT_MIN_LINES: u64 = 1000;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum Line {
+    Put { record: TimeRecord },
+    Delete { id: RecordId },
+}
+
+/// A line with only what a scan needs, cheaper to read than [`Line`].
+#[derive(Debug, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum LineId {
+    Put { record: RecordIdOnly },
+    Delete { id: RecordId },
+}
+
+#[derive(Debug, Deserialize)]
+struct RecordIdOnly {
+    id: RecordId,
+}
+
+/// What a pass over the file found, without keeping the records.
+#[derive(Debug, Default)]
+struct Scan {
+    /// Line number of the latest "put" of every record that was not deleted
+    live: HashMap<RecordId, u64>,
+    /// Number of complete lines
+    lines: u64,
+    /// Length of the complete lines in bytes, a torn last line is left out
+    len: u64,
+}
+
+impl Scan {
+    /// Whether most lines no longer hold a record.
+    fn needs_compaction(&self) -> bool {
+        self.lines >= COMPACT_MIN_LINES && self.lines > 2 * self.live.len() as u64
+    }
+}
+
+pub struct JsonlTracker {
+    database: PathBuf,
+    lockfile: PathBuf,
+    lock: ReentrantLock,
+}
+
+impl Reporter for JsonlTracker {}
+
+impl JsonlTracker {
+    pub fn new<D, L>(database: D, lockfile: L) -> Self
+    where
+        D: Into<PathBuf>,
+        L: Into<PathBuf>,
+    {
+        let database = database.into();
+        let lockfile = lockfile.into();
+        let lock = ReentrantLock::new(with_suffix(&database, ".lock"), LOCK_TIMEOUT);
+
+        JsonlTracker { database, lockfile, lock }
+    }
+
+    /// Sets how long to wait for other processes using the same database.
+    pub fn with_lock_timeout(mut self, timeout: Duration) -> Self {
+        self.lock.set_timeout(timeout);
+        self
+    }
+
+    /// Rewrites the file with one line per record, dropping replaced and
+    /// deleted records. Happens by itself once most lines are outdated. Fails
+    /// without changing the file if any record cannot be read.
+    pub fn compact(&mut self) -> Result<(), TrackerError> {
+        let _lock = self.lock.acquire()?;
+        let Some((file, scan)) = self.open()? else {
+            return Ok(());
+        };
+
+        self.rewrite(stream(file, scan)?)
+            .attach_printable("unable to compact database")
+    }
+
+    /// Replaces the file with one "put" line per record, through a temporary
+    /// file so readers never see it half written.
+    fn rewrite<I>(&self, records: I) -> Result<(), TrackerError>
+    where
+        I: IntoIterator<Item = Result<TimeRecord, TrackerError>>,
+    {
+        let temp = with_suffix(&self.database, ".tmp");
+        let write = || {
+            let mut out = std::io::BufWriter::new(
+                OpenOptions::new()
+                    .write(true)
+                    .create(true)
+                    .truncate(true)
+                    .open(&temp)
+                    .change_context(TrackerError)
+                    .attach_printable("unable to open temporary database")?,
+            );
+            for record in records {
+                write_line(&mut out, &Line::Put { record: record? })?;
+            }
+            out.into_inner()
+                .map_err(|e| e.into_error())
+                .and_then(|file| file.sync_all())
+                .change_context(TrackerError)
+                .attach_printable("unable to write temporary database")
+        };
+        if let Err(e) = write() {
+            let _ = std::fs::remove_file(&temp);
+            return Err(e);
+        }
+
+        std::fs::rename(&temp, &self.database)
+            .change_context(TrackerError)
+            .attach_printable("unable to replace database")?;
+        sync_parent_dir(&self.database);
+
+        Ok(())
+    }
+
+    /// Opens the file and scans it, or `None` if there is no file yet.
+    fn open(&self) -> Result<Option<(File, Scan)>, TrackerError> {
+        let file = match File::open(&self.database) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => {
+                return Err(e)
+                    .change_context(TrackerError)
+                    .attach_printable("unable to open database when reading")
+            }
+        };
+        let scan = scan(&file).attach_printable_lazy(|| format!("database: {}", self.database.display()))?;
+
+        Ok(Some((file, scan)))
+    }
+
+    /// The record with the given ID, if it exists, and the scan that found it.
+    fn find_live(&self, id: RecordId) -> Result<(TimeRecord, Scan), TrackerError> {
+        let not_found = || Report::new(TrackerError).attach_printable(format!("no record with ID {id}"));
+
+        let (mut file, scan) = self.open()?.ok_or_else(not_found)?;
+        let number = *scan.live.get(&id).ok_or_else(not_found)?;
+
+        file.seek(SeekFrom::Start(0))
+            .change_context(TrackerError)
+            .attach_printable("unable to rewind database")?;
+        let line = BufReader::new(file)
+            .lines()
+            .nth(number as usize)
+            .ok_or_else(not_found)?
+            .change_context(TrackerError)
+            .attach_printable("unable to read database")?;
+
+        match serde_json::from_str(&line) {
+            Ok(Line::Put { record }) => Ok((record, scan)),
+            _ => Err(not_found()),
+        }
+    }
+
+    /// Appends lines in one write. A line torn by a crash during an earlier
+    /// append is cut off first.
+    fn append(&self, lines: &[Line]) -> Result<(), TrackerError> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(&self.database)
+            .change_context(TrackerError)
+            .attach_printable("unable to open database when appending")?;
+        cut_torn_line(&mut file)?;
+
+        let mut buf = Vec::new();
+        for line in lines {
+            write_line(&mut buf, line)?;
+        }
+
+        file.write_all(&buf)
+            .and_then(|()| file.sync_data())
+            .change_context(TrackerError)
+            .attach_printable("unable to append to database")
+    }
+
+    /// Compacts the file if a scan found mostly outdated lines.
+    fn compact_if_needed(&mut self, scan: &Scan) -> Result<(), TrackerError> {
+        if scan.needs_compaction() {
+            self.compact().attach_printable("unable to compact database")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Tracker for JsonlTracker {
+    fn lock_storage(&self) -> Result<StorageLock, TrackerError> {
+        self.lock.acquire().map(StorageLock::from)
+    }
+
+    fn start_at(&mut self, task: Task, start: StartTime) -> Result<StartupStatus, TrackerError> {
+        let _lock = self.lock.acquire()?;
+        start_session(&self.lockfile, Session::new(start, task))
+    }
+
+    fn is_running(&self) -> bool {
+        self.lockfile.exists()
+    }
+
+    fn session(&self) -> Result<Option<Session>, TrackerError> {
+        let _lock = self.lock.acquire()?;
+        read_session(&self.lockfile)
+    }
+
+    fn stop_at(&mut self, end: EndTime) -> Result<TimeRecord, TrackerError> {
+        let _lock = self.lock.acquire()?;
+        let session = read_session(&self.lockfile)?
+            .ok_or(TrackerError)
+            .attach_printable("tracker is not running")?;
+        let record = session.finish(end)?;
+
+        self.append(&[Line::Put { record: record.clone() }])?;
+        write_session(&self.lockfile, None)?;
+
+        Ok(record)
+    }
+
+    fn switch_at(&mut self, task: Task, at: StartTime) -> Result<Option<TimeRecord>, TrackerError> {
+        let _lock = self.lock.acquire()?;
+        let Some(session) = read_session(&self.lockfile)? else {
+            self.start_at(task, at)?;
+            return Ok(None);
+        };
+        let record = session.finish(at.0.into())?;
+
+        self.append(&[Line::Put { record: record.clone() }])?;
+        write_session(&self.lockfile, Some(Session::new(at, task)))?;
+
+        Ok(Some(record))
+    }
+
+    fn restore_session(&mut self, session: Option<Session>) -> Result<(), TrackerError> {
+        let _lock = self.lock.acquire()?;
+        write_session(&self.lockfile, session)
+    }
+
+    fn has_records(&self) -> Result<bool, TrackerError> {
+        let _lock = self.lock.acquire()?;
+        Ok(self.open()?.is_some_and(|(_, scan)| !scan.live.is_empty()))
+    }
+
+    /// Reads the records while the files are locked, so that a compaction
+    /// in another process cannot replace the file halfway through.
+    fn records(&self) -> Result<impl Iterator<Item = TimeRecord>, TrackerError> {
+        let _lock = self.lock.acquire()?;
+        let records = match self.open()? {
+            Some((file, scan)) => stream(file, scan)?.collect::<Result<Vec<_>, _>>()?,
+            None => Vec::new(),
+        };
+
+        Ok(records.into_iter())
+    }
+
+    fn add(&mut self, record: TimeRecord) -> Result<Vec<TimeRecord>, TrackerError> {
+        record.validate()?;
+
+        let _lock = self.lock.acquire()?;
+        let overlaps = match self.open()? {
+            Some((_, scan)) if scan.live.contains_key(&record.id) => {
+                return Err(Report::new(TrackerError))
+                    .attach_printable(format!("record {} already exists", record.id));
+            }
+            Some((file, scan)) => stream(file, scan)?
+                .filter(|existing| existing.as_ref().map_or(true, |existing| existing.overlaps(&record)))
+                .collect::<Result<_, _>>()?,
+            None => Vec::new(),
+        };
+
+        self.append(&[Line::Put { record }])?;
+
+        Ok(overlaps)
+    }
+
+    fn import<I>(&mut self, records: I) -> Result<usize, TrackerError>
+    where
+        I: IntoIterator<Item = TimeRecord>,
+    {
+        let _lock = self.lock.acquire()?;
+        let mut ids = match self.open()? {
+            Some((_, scan)) => scan.live.into_keys().collect(),
+            None => HashSet::new(),
+        };
+
+        let mut lines = Vec::new();
+        for record in records {
+            record.validate()?;
+            if !ids.insert(record.id) {
+                return Err(Report::new(TrackerError))
+                    .attach_printable(format!("record {} already exists", record.id));
+            }
+            lines.push(Line::Put { record });
+        }
+        self.append(&lines)?;
+
+        Ok(lines.len())
+    }
+
+    fn clear(&mut self) -> Result<(), TrackerError> {
+        let _lock = self.lock.acquire()?;
+        self.rewrite(std::iter::empty())
+            .attach_printable("unable to empty database")
+    }
+
+    fn replace(&mut self, record: TimeRecord) -> Result<TimeRecord, TrackerError> {
+        record.validate()?;
+
+        let _lock = self.lock.acquire()?;
+        let (previous, mut scan) = self.find_live(record.id)?;
+        self.append(&[Line::Put { record }])?;
+
+        scan.lines += 1;
+        self.compact_if_needed(&scan)?;
+
+        Ok(previous)
+    }
+
+    fn delete(&mut self, id: RecordId) -> Result<TimeRecord, TrackerError> {
+        let _lock = self.lock.acquire()?;
+        let (deleted, mut scan) = self.find_live(id)?;
+        self.append(&[Line::Delete { id }])?;
+
+        scan.lines += 1;
+        scan.live.remove(&id);
+        self.compact_if_needed(&scan)?;
+
+        Ok(deleted)
+    }
+}
+
+/// Reads every line of the file, keeping only where each record is.
+fn scan(file: &File) -> Result<Scan, TrackerError> {
+    let mut reader = BufReader::new(file);
+    let mut scan = Scan::default();
+    let mut buf = String::new();
+
+    loop {
+        buf.clear();
+        let read = reader
+            .read_line(&mut buf)
+            .change_context(TrackerError)
+            .attach_printable("unable to read database")?;
+        if read == 0 {
+            break;
+        }
+        if !buf.ends_with('\n') {
+            tracing::warn!(line = scan.lines + 1, "ignoring the incomplete last line of the database");
+            break;
+        }
+
+        let line = serde_json::from_str::<LineId>(&buf)
+            .change_context(TrackerError)
+            .attach_printable_lazy(|| format!("unable to deserialize line {} of the database", scan.lines + 1))?;
+        match line {
+            LineId::Put { record } => {
+                scan.live.insert(record.id, scan.lines);
+            }
+            LineId::Delete { id } => {
+                scan.live.remove(&id);
+            }
+        }
+
+        scan.lines += 1;
+        scan.len += read as u64;
+    }
+
+    Ok(scan)
+}
+
+/// The live records of a scanned file, read line by line. Lines that were
+/// replaced or deleted later are skipped without reading them, a line that
+/// cannot be read is an error.
+fn stream(
+    mut file: File,
+    scan: Scan,
+) -> Result<impl Iterator<Item = Result<TimeRecord, TrackerError>>, TrackerError> {
+    file.seek(SeekFrom::Start(0))
+        .change_context(TrackerError)
+        .attach_printable("unable to rewind database")?;
+
+    let live = scan.live.into_values().collect::<HashSet<_>>();
+    let lines = BufReader::new(file.take(scan.len)).lines();
+
+    Ok(lines
+        .zip(0..)
+        .filter(move |(_, number)| live.contains(number))
+        .map(|(line, number)| {
+            let line = line
+                .change_context(TrackerError)
+                .attach_printable("unable to read database")?;
+            match serde_json::from_str(&line) {
+                Ok(Line::Put { record }) => Ok(record),
+                Ok(Line::Delete { .. }) => Err(Report::new(TrackerError).attach_printable("expected a record")),
+                Err(e) => Err(Report::new(e).change_context(TrackerError)),
+            }
+            .attach_printable_lazy(|| format!("unable to deserialize line {} of the database", number + 1))
+        }))
+}
+
+fn write_line<W>(out: &mut W, line: &Line) -> Result<(), TrackerError>
+where
+    W: Write,
+{
+    serde_json::to_writer(&mut *out, line)
+        .change_context(TrackerError)
+        .attach_printable("unable to serialize database line")?;
+
+    out.write_all(b"\n")
+        .change_context(TrackerError)
+        .attach_printable("unable to write database line")
+}
+
+/// Truncates the file after its last line break, dropping a line that was
+/// only partly written.
+fn cut_torn_line(file: &mut File) -> Result<(), TrackerError> {
+    const CHUNK: u64 = 4096;
+
+    let len = file
+        .metadata()
+        .change_context(TrackerError)
+        .attach_printable("unable to read database metadata")?
+        .len();
+
+    let mut end = len;
+    let mut buf = vec![0; CHUNK as usize];
+    while end > 0 {
+        let start = end.saturating_sub(CHUNK);
+        let chunk = &mut buf[..(end - start) as usize];
+        file.seek(SeekFrom::Start(start))
+            .and_then(|_| file.read_exact(chunk))
+            .change_context(TrackerError)
+            .attach_printable("unable to read the end of the database")?;
+
+        if let Some(position) = chunk.iter().rposition(|&byte| byte == b'\n') {
+            end = start + position as u64 + 1;
+            break;
+        }
+        end = start;
+    }
+
+    if end < len {
+        tracing::warn!(bytes = len - end, "removing the incomplete last line of the database");
+        file.set_len(end)
+            .change_context(TrackerError)
+            .attach_printable("unable to remove incomplete line from database")?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_fs::{prelude::PathChild, TempDir};
+
+    use super::*;
+    use crate::feature::tracker::tlib::tracker_tests;
+
+    fn new_tracker(temp: &TempDir) -> JsonlTracker {
+        JsonlTracker::new(temp.child("records.jsonl").to_path_buf(), temp.child("lockfile").to_path_buf())
+    }
+
+    tracker_tests!(new_tracker);
+
+    fn record() -> TimeRecord {
+        TimeRecord {
+            id: RecordId::new(),
+            start: StartTime::now(),
+            end: (StartTime::now().instant() + chrono::TimeDelta::minutes(1)).into(),
+            pauses: Vec::new(),
+            task: Task::default(),
+        }
+    }
+
+    fn lines(temp: &TempDir) -> usize {
+        std::fs::read_to_string(temp.child("records.jsonl")).unwrap().lines().count()
+    }
+
+    #[test]
+    fn stopping_appends_one_line() {
+        let temp = TempDir::new().unwrap();
+        let mut tracker = new_tracker(&temp);
+        tracker.import(vec![record(), record()]).unwrap();
+
+        tracker.start(Task::default()).unwrap();
+        tracker.stop().unwrap();
+
+        assert_eq!(lines(&temp), 3);
+    }
+
+    #[test]
+    fn compaction_keeps_only_live_records() {
+        let temp = TempDir::new().unwrap();
+        let mut tracker = new_tracker(&temp);
+        let mut kept = record();
+        let deleted = record();
+        tracker.import(vec![kept.clone(), deleted.clone()]).unwrap();
+
+        kept.task.project = Some("website".to_string());
+        tracker.replace(kept.clone()).unwrap();
+        tracker.delete(deleted.id).unwrap();
+        assert_eq!(lines(&temp), 4);
+
+        tracker.compact().unwrap();
+
+        assert_eq!(lines(&temp), 1);
+        assert_eq!(tracker.records().unwrap().collect::<Vec<_>>(), [kept]);
+    }
+
+    #[test]
+    fn mostly_outdated_file_is_compacted() {
+        let temp = TempDir::new().unwrap();
+        let mut tracker = new_tracker(&temp);
+        let records = (0..COMPACT_MIN_LINES).map(|_| record()).collect::<Vec<_>>();
+        tracker.import(records.clone()).unwrap();
+
+        // 1333 lines for 667 records
+        for record in &records[..333] {
+            tracker.delete(record.id).unwrap();
+        }
+        assert_eq!(lines(&temp), 1333);
+
+        // 1334 lines for 666 records
+        tracker.delete(records[333].id).unwrap();
+        assert_eq!(lines(&temp), 666);
+        assert_eq!(tracker.records().unwrap().count(), 666);
+    }
+
+    #[test]
+    fn torn_last_line_is_ignored_and_cut_off() {
+        let temp = TempDir::new().unwrap();
+        let mut tracker = new_tracker(&temp);
+        let first = record();
+        tracker.add(first.clone()).unwrap();
+
+        // A crash in the middle of an append
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(temp.child("records.jsonl"))
+            .unwrap();
+        file.write_all(br#"{"op":"put","record":{"id":"#).unwrap();
+
+        assert_eq!(tracker.records().unwrap().collect::<Vec<_>>(), vec![first.clone()]);
+
+        let second = record();
+        tracker.add(second.clone()).unwrap();
+        assert_eq!(tracker.records().unwrap().collect::<Vec<_>>(), [first, second]);
+        assert_eq!(lines(&temp), 2);
+    }
+
+    #[test]
+    fn compaction_keeps_a_record_it_cannot_read() {
+        let temp = TempDir::new().unwrap();
+        let mut tracker = new_tracker(&temp);
+        tracker.import(vec![record()]).unwrap();
+
+        // A line with a readable ID but a broken record
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(temp.child("records.jsonl"))
+            .unwrap();
+        writeln!(file, r#"{{"op":"put","record":{{"id":"{}","start":"soon"}}}}"#, RecordId::new()).unwrap();
+        let before = std::fs::read_to_string(temp.child("records.jsonl")).unwrap();
+
+        assert!(tracker.records().is_err());
+        assert!(tracker.compact().is_err());
+        assert_eq!(std::fs::read_to_string(temp.child("records.jsonl")).unwrap(), before);
+        assert!(!temp.child("records.jsonl.tmp").exists());
+    }
+
+    #[test]
+    fn corrupted_line_is_an_error() {
+        let temp = TempDir::new().unwrap();
+        let tracker = new_tracker(&temp);
+
+        std::fs::write(temp.child("records.jsonl"), "not json\n").unwrap();
+
+        assert!(tracker.records().is_err());
+    }
+}
@@ -0,0 +1,203 @@
+use std::{cmp::Ordering, fmt, str::FromStr};
+
+use super::ReportEntry;
+
+/// What to sort a listing of records by. Records that compare equal stay in
+/// order of their start.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SortBy {
+    /// When the record started
+    #[default]
+    Start,
+    /// When the record ended, the running session last
+    End,
+    /// Tracked time
+    Duration,
+    /// Project name, records without a project last
+    Project,
+}
+
+impl SortBy {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SortBy::Start => "start",
+            SortBy::End => "end",
+            SortBy::Duration => "duration",
+            SortBy::Project => "project",
+        }
+    }
+
+    fn compare(&self, a: &ReportEntry, b: &ReportEntry) -> Ordering {
+        let ordering = match self {
+            SortBy::Start => Ordering::Equal,
+            SortBy::End => (a.end.is_none(), a.end).cmp(&(b.end.is_none(), b.end)),
+            SortBy::Duration => a.duration.cmp(&b.duration),
+            SortBy::Project => {
+                let key = |entry: &ReportEntry| (entry.task.project.is_none(), entry.task.project.clone());
+                key(a).cmp(&key(b))
+            }
+        };
+
+        ordering.then(a.start.cmp(&b.start))
+    }
+}
+
+impl fmt::Display for SortBy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("unknown sort order '{0}', expected one of: start, end, duration, project")]
+pub struct UnknownSortBy(String);
+
+impl FromStr for SortBy {
+    type Err = UnknownSortBy;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "start" => Ok(SortBy::Start),
+            "end" => Ok(SortBy::End),
+            "duration" => Ok(SortBy::Duration),
+            "project" => Ok(SortBy::Project),
+            _ => Err(UnknownSortBy(s.to_string())),
+        }
+    }
+}
+
+/// Which records to list. Every condition that is set must hold.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EntryFilter {
+    /// Exact project name
+    pub project: Option<String>,
+    /// Tags the record must all have
+    pub tags: Vec<String>,
+    /// Text the description contains, ignoring case
+    pub text: Option<String>,
+}
+
+impl EntryFilter {
+    pub fn matches(&self, entry: &ReportEntry) -> bool {
+        let project = self
+            .project
+            .as_ref()
+            .is_none_or(|project| entry.task.project.as_ref() == Some(project));
+        let tags = self.tags.iter().all(|tag| entry.task.tags.contains(tag));
+        let text = self.text.as_ref().is_none_or(|text| {
+            entry
+                .task
+                .description
+                .as_ref()
+                .is_some_and(|description| description.to_lowercase().contains(&text.to_lowercase()))
+        });
+
+        project && tags && text
+    }
+}
+
+/// Filters and sorts report entries for a listing, keeping at most `limit`
+/// entries from the front of the sorted list.
+pub fn list_entries(
+    entries: Vec<ReportEntry>,
+    filter: &EntryFilter,
+    sort_by: SortBy,
+    reverse: bool,
+    limit: Option<usize>,
+) -> Vec<ReportEntry> {
+    let mut entries = entries
+        .into_iter()
+        .filter(|entry| filter.matches(entry))
+        .collect::<Vec<_>>();
+
+    entries.sort_by(|a, b| sort_by.compare(a, b));
+    if reverse {
+        entries.reverse();
+    }
+    if let Some(limit) = limit {
+        entries.truncate(limit);
+    }
+
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use chrono::{DateTime, Utc};
+
+    use super::*;
+    use crate::feature::tracker::{RecordId, Task};
+
+    fn entry(start: &str, minutes: u64, project: Option<&str>, tags: &[&str], description: &str) -> ReportEntry {
+        let start = DateTime::parse_from_rfc3339(start).unwrap().with_timezone(&Utc);
+        let duration = Duration::from_secs(minutes * 60);
+
+        ReportEntry {
+            id: Some(RecordId::new()),
+            start: start.into(),
+            end: Some((start + duration).into()),
+            duration,
+            task: Task {
+                project: project.map(str::to_string),
+                description: Some(description.to_string()),
+                tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            },
+        }
+    }
+
+    fn entries() -> Vec<ReportEntry> {
+        vec![
+            entry("2026-10-15T09:00:00Z", 90, Some("website"), &["css"], "Fix header"),
+            entry("2026-10-15T11:00:00Z", 30, None, &[], "Emails"),
+            entry("2026-10-15T13:00:00Z", 60, Some("blog"), &["css", "draft"], "Header post"),
+        ]
+    }
+
+    fn descriptions(entries: &[ReportEntry]) -> Vec<&str> {
+        entries
+            .iter()
+            .map(|entry| entry.task.description.as_deref().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn filter_conditions_must_all_hold() {
+        let filter = EntryFilter {
+            tags: vec!["css".to_string()],
+            text: Some("header".to_string()),
+            ..Default::default()
+        };
+
+        let listed = list_entries(entries(), &filter, SortBy::Start, false, None);
+        assert_eq!(descriptions(&listed), ["Fix header", "Header post"]);
+
+        let filter = EntryFilter {
+            project: Some("blog".to_string()),
+            ..filter
+        };
+        let listed = list_entries(entries(), &filter, SortBy::Start, false, None);
+        assert_eq!(descriptions(&listed), ["Header post"]);
+    }
+
+    #[test]
+    fn sorted_by_project_puts_records_without_project_last() {
+        let listed = list_entries(entries(), &EntryFilter::default(), SortBy::Project, false, None);
+
+        assert_eq!(descriptions(&listed), ["Header post", "Fix header", "Emails"]);
+    }
+
+    #[test]
+    fn limit_applies_after_sorting() {
+        let listed = list_entries(entries(), &EntryFilter::default(), SortBy::Duration, true, Some(2));
+
+        assert_eq!(descriptions(&listed), ["Fix header", "Header post"]);
+    }
+
+    #[test]
+    fn parses_sort_order() {
+        assert_eq!("Duration".parse::<SortBy>().unwrap(), SortBy::Duration);
+        assert!("length".parse::<SortBy>().is_err());
+    }
+}
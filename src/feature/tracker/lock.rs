@@ -0,0 +1,161 @@
+// advisory locks between `track` processes
+// 1 empty file per locked resource, e.g. "db.json.lock" next to the database.
+// The operating system releases the lock when the process exits, so a
+// crashed process never leaves the resource locked.
+
+use std::{
+    fs::{File, OpenOptions, TryLockError},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, Weak},
+    time::{Duration, Instant},
+};
+
+use error_stack::{Report, Result, ResultExt};
+
+use crate::error::Suggestion;
+
+use super::TrackerError;
+
+/// How long to wait for another process to release a lock.
+pub const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often to try to take a lock that is held by another process.
+const RETRY_INTERVAL: Duration = Duration::from_millis(5);
+
+/// Exclusive advisory lock on a file, released when dropped.
+#[derive(Debug)]
+pub struct FileLock {
+    _file: File,
+}
+
+impl FileLock {
+    /// Takes the lock on `path`, creating the file if needed. Waits up to
+    /// `timeout` for other processes to release it.
+    pub fn acquire(path: &Path, timeout: Duration) -> Result<FileLock, TrackerError> {
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .change_context(TrackerError)
+            .attach_printable_lazy(|| format!("unable to open lock file {}", path.display()))?;
+
+        let deadline = Instant::now() + timeout;
+        loop {
+            match file.try_lock() {
+                Ok(()) => return Ok(FileLock { _file: file }),
+                Err(TryLockError::WouldBlock) if Instant::now() < deadline => std::thread::sleep(RETRY_INTERVAL),
+                Err(TryLockError::WouldBlock) => {
+                    return Err(Report::new(TrackerError))
+                        .attach_printable(format!(
+                            "timed out after {}s waiting for the lock on {}",
+                            timeout.as_secs_f32(),
+                            path.display()
+                        ))
+                        .attach(Suggestion("another track command is using the database, try again when it has finished"));
+                }
+                Err(TryLockError::Error(e)) => {
+                    return Err(e)
+                        .change_context(TrackerError)
+                        .attach_printable_lazy(|| format!("unable to lock {}", path.display()))
+                }
+            }
+        }
+    }
+}
+
+/// Guard returned by [`Tracker::lock_storage`](super::Tracker::lock_storage),
+/// the storage stays locked until it is dropped.
+#[derive(Debug, Default)]
+#[must_use]
+pub struct StorageLock {
+    _lock: Option<Arc<FileLock>>,
+}
+
+impl From<Arc<FileLock>> for StorageLock {
+    fn from(lock: Arc<FileLock>) -> Self {
+        StorageLock { _lock: Some(lock) }
+    }
+}
+
+/// A lock that can be taken again while it is held, e.g. by a step of a
+/// change that already holds it. Locks on the same file are not shared
+/// between open files, so taking it twice from one process would wait for
+/// itself.
+#[derive(Debug)]
+pub struct ReentrantLock {
+    path: PathBuf,
+    timeout: Duration,
+    held: Mutex<Weak<FileLock>>,
+}
+
+impl ReentrantLock {
+    pub fn new(path: PathBuf, timeout: Duration) -> Self {
+        ReentrantLock {
+            path,
+            timeout,
+            held: Mutex::new(Weak::new()),
+        }
+    }
+
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Takes the lock, or shares it if it is already held. It is released
+    /// when every returned guard is dropped.
+    pub fn acquire(&self) -> Result<Arc<FileLock>, TrackerError> {
+        let mut held = self.held.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(lock) = held.upgrade() {
+            return Ok(lock);
+        }
+
+        let lock = Arc::new(FileLock::acquire(&self.path, self.timeout)?);
+        *held = Arc::downgrade(&lock);
+
+        Ok(lock)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_fs::{prelude::PathChild, TempDir};
+
+    use super::*;
+
+    #[test]
+    fn held_lock_times_out() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.child("db.lock");
+
+        let _held = FileLock::acquire(&path, LOCK_TIMEOUT).unwrap();
+
+        let started = Instant::now();
+        assert!(FileLock::acquire(&path, Duration::from_millis(50)).is_err());
+        assert!(started.elapsed() >= Duration::from_millis(50));
+    }
+
+    #[test]
+    fn lock_is_released_when_dropped() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.child("db.lock");
+
+        drop(FileLock::acquire(&path, LOCK_TIMEOUT).unwrap());
+
+        assert!(FileLock::acquire(&path, Duration::ZERO).is_ok());
+    }
+
+    #[test]
+    fn reentrant_lock_is_shared_while_held() {
+        let temp = TempDir::new().unwrap();
+        let lock = ReentrantLock::new(temp.child("db.lock").to_path_buf(), Duration::ZERO);
+
+        let outer = lock.acquire().unwrap();
+        let inner = lock.acquire().unwrap();
+        assert!(FileLock::acquire(&temp.child("db.lock"), Duration::ZERO).is_err());
+
+        drop(inner);
+        drop(outer);
+        assert!(FileLock::acquire(&temp.child("db.lock"), Duration::ZERO).is_ok());
+    }
+}
@@ -0,0 +1,137 @@
+// in-memory tracker
+// Nothing is stored, the records and the session are gone when the tracker
+// is dropped. Useful for tests and for trying commands without touching the
+// database.
+
+use error_stack::{Report, Result, ResultExt};
+
+use super::{
+    EndTime, RecordId, Reporter, Session, StartTime, StartupStatus, Task, TimeRecord, Tracker, TrackerError,
+};
+
+#[derive(Debug, Default)]
+pub struct MemoryTracker {
+    session: Option<Session>,
+    records: Vec<TimeRecord>,
+}
+
+impl Reporter for MemoryTracker {}
+
+impl MemoryTracker {
+    pub fn with_records(records: Vec<TimeRecord>) -> Self {
+        MemoryTracker { session: None, records }
+    }
+}
+
+impl Tracker for MemoryTracker {
+    fn start_at(&mut self, task: Task, start: StartTime) -> Result<StartupStatus, TrackerError> {
+        if self.session.is_some() {
+            return Ok(StartupStatus::Running);
+        }
+
+        self.session = Some(Session::new(start, task));
+        Ok(StartupStatus::Started)
+    }
+
+    fn is_running(&self) -> bool {
+        self.session.is_some()
+    }
+
+    fn session(&self) -> Result<Option<Session>, TrackerError> {
+        Ok(self.session.clone())
+    }
+
+    fn stop_at(&mut self, end: EndTime) -> Result<TimeRecord, TrackerError> {
+        let session = self
+            .session
+            .clone()
+            .ok_or(TrackerError)
+            .attach_printable("tracker is not running")?;
+
+        let record = session.finish(end)?;
+        self.session = None;
+        self.records.push(record.clone());
+        Ok(record)
+    }
+
+    fn switch_at(&mut self, task: Task, at: StartTime) -> Result<Option<TimeRecord>, TrackerError> {
+        let record = match self.session.clone() {
+            Some(session) => Some(session.finish(at.0.into())?),
+            None => None,
+        };
+
+        self.records.extend(record.clone());
+        self.session = Some(Session::new(at, task));
+        Ok(record)
+    }
+
+    fn restore_session(&mut self, session: Option<Session>) -> Result<(), TrackerError> {
+        self.session = session;
+        Ok(())
+    }
+
+    fn has_records(&self) -> Result<bool, TrackerError> {
+        Ok(!self.records.is_empty())
+    }
+
+    fn records(&self) -> Result<impl Iterator<Item = TimeRecord>, TrackerError> {
+        Ok(self.records.iter().cloned())
+    }
+
+    fn add(&mut self, record: TimeRecord) -> Result<Vec<TimeRecord>, TrackerError> {
+        record.validate()?;
+        if self.records.iter().any(|existing| existing.id == record.id) {
+            return Err(Report::new(TrackerError)).attach_printable(format!("record {} already exists", record.id));
+        }
+
+        let overlaps = self
+            .records
+            .iter()
+            .filter(|existing| existing.overlaps(&record))
+            .cloned()
+            .collect();
+        self.records.push(record);
+        Ok(overlaps)
+    }
+
+    fn clear(&mut self) -> Result<(), TrackerError> {
+        self.records.clear();
+        Ok(())
+    }
+
+    fn replace(&mut self, record: TimeRecord) -> Result<TimeRecord, TrackerError> {
+        record.validate()?;
+
+        let existing = self
+            .records
+            .iter_mut()
+            .find(|existing| existing.id == record.id)
+            .ok_or(TrackerError)
+            .attach_printable(format!("no record with ID {}", record.id))?;
+        Ok(std::mem::replace(existing, record))
+    }
+
+    fn delete(&mut self, id: RecordId) -> Result<TimeRecord, TrackerError> {
+        let index = self
+            .records
+            .iter()
+            .position(|record| record.id == id)
+            .ok_or(TrackerError)
+            .attach_printable(format!("no record with ID {id}"))?;
+        Ok(self.records.remove(index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_fs::TempDir;
+
+    use super::*;
+    use crate::feature::tracker::tlib::tracker_tests;
+
+    fn new_tracker(_temp: &TempDir) -> MemoryTracker {
+        MemoryTracker::default()
+    }
+
+    tracker_tests!(new_tracker);
+}
@@ -0,0 +1,139 @@
+// format versions of the flat file database
+// The version is stored in the "version" field of the database. Files
+// written before it existed have no such field and are version 0. Every
+// migration upgrades the raw JSON by one version, so files of any older
+// version are read by applying the migrations after their version in order.
+
+use error_stack::{Report, Result, ResultExt};
+use serde_json::{Map, Value};
+use uuid::Uuid;
+
+use crate::error::Suggestion;
+
+use super::TrackerError;
+
+/// Upgrades a database from one version to the next.
+pub struct Migration {
+    /// What changes, shown by `track db migrate`
+    pub description: &'static str,
+    apply: fn(&mut Map<String, Value>) -> Result<(), TrackerError>,
+}
+
+/// Migrations by the version they upgrade from: the first upgrades version 0
+/// to version 1.
+const MIGRATIONS: &[Migration] = &[Migration {
+    description: "give records written before IDs existed an ID",
+    apply: assign_missing_ids,
+}];
+
+/// Version of the databases written by this build.
+pub const FORMAT_VERSION: u32 = MIGRATIONS.len() as u32;
+
+/// Version of a database, 0 if it has none.
+pub fn version_of(db: &Value) -> Result<u32, TrackerError> {
+    match db.get("version") {
+        None => Ok(0),
+        Some(version) => version
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or_else(|| Report::new(TrackerError).attach_printable(format!("invalid database version {version}"))),
+    }
+}
+
+/// Migrations that upgrade a database of the given version to
+/// [`FORMAT_VERSION`], in order. Fails for versions written by a newer
+/// build, since they cannot be read without losing data.
+pub fn pending(version: u32) -> Result<&'static [Migration], TrackerError> {
+    MIGRATIONS.get(version as usize..).ok_or_else(|| {
+        Report::new(TrackerError)
+            .attach_printable(format!(
+                "database has version {version}, but this version of track only reads up to version {FORMAT_VERSION}"
+            ))
+            .attach(Suggestion("update track to use this database"))
+    })
+}
+
+/// Upgrades a database to [`FORMAT_VERSION`] and returns the version it had.
+pub fn migrate(db: &mut Value) -> Result<u32, TrackerError> {
+    let version = version_of(db)?;
+    let migrations = pending(version)?;
+
+    let fields = db
+        .as_object_mut()
+        .ok_or(TrackerError)
+        .attach_printable("database is not a JSON object")?;
+    for (migration, to) in migrations.iter().zip(version + 1..) {
+        (migration.apply)(fields)
+            .attach_printable_lazy(|| format!("unable to migrate database to version {to}"))?;
+        fields.insert("version".to_string(), to.into());
+    }
+
+    Ok(version)
+}
+
+// IDs were added without a version, so they used to be assigned on every
+// load of a version 0 database.
+fn assign_missing_ids(db: &mut Map<String, Value>) -> Result<(), TrackerError> {
+    let records = db
+        .get_mut("records")
+        .and_then(Value::as_array_mut)
+        .into_iter()
+        .flatten();
+
+    for record in records {
+        let record = record
+            .as_object_mut()
+            .ok_or(TrackerError)
+            .attach_printable("record is not a JSON object")?;
+        let has_id = record
+            .get("id")
+            .and_then(Value::as_str)
+            .and_then(|id| Uuid::parse_str(id).ok())
+            .is_some_and(|id| !id.is_nil());
+        if !has_id {
+            record.insert("id".to_string(), Uuid::new_v4().to_string().into());
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn database_without_version_is_version_0() {
+        assert_eq!(version_of(&json!({ "records": [] })).unwrap(), 0);
+        assert_eq!(version_of(&json!({ "version": 1, "records": [] })).unwrap(), 1);
+        assert!(version_of(&json!({ "version": "1" })).is_err());
+    }
+
+    #[test]
+    fn migrating_sets_the_current_version() {
+        let mut db = json!({ "records": [{ "start": "2024-11-23T18:02:11Z", "end": "2024-11-23T19:02:11Z" }] });
+
+        assert_eq!(migrate(&mut db).unwrap(), 0);
+
+        assert_eq!(db["version"], FORMAT_VERSION);
+        assert!(db["records"][0]["id"].is_string());
+    }
+
+    #[test]
+    fn current_database_is_left_alone() {
+        let original = json!({ "version": FORMAT_VERSION, "records": [] });
+        let mut db = original.clone();
+
+        assert_eq!(migrate(&mut db).unwrap(), FORMAT_VERSION);
+        assert_eq!(db, original);
+    }
+
+    #[test]
+    fn newer_database_is_an_error() {
+        let mut db = json!({ "version": FORMAT_VERSION + 1, "records": [] });
+
+        assert!(migrate(&mut db).is_err());
+    }
+}
@@ -1,35 +1,335 @@
+mod backend;
+mod breakdown;
 mod flatfile;
+mod journal;
+mod jsonl;
+mod listing;
+mod lock;
+mod memory;
+mod migration;
+mod origin;
 mod reporter;
+mod sqlite;
 
-use chrono::Utc;
+use std::collections::BTreeSet;
+
+use chrono::{DateTime, TimeDelta, Utc};
 use serde::{Deserialize, Serialize};
-use error_stack::Result;
+use error_stack::{Report, Result, ResultExt};
+use uuid::Uuid;
+
+use crate::error::Suggestion;
 
-pub use flatfile::FlatFileTracker;
-pub use reporter::{Reporter, ReporterError,ReportTimespan};
+pub use backend::{convert, AnyTracker, BackendKind, BackendSpec, Conversion, InvalidBackendSpec, UnknownBackend};
+pub use breakdown::{Breakdown, Group, GroupBy, UnknownGroupBy, NO_PROJECT, NO_TAG};
+pub use flatfile::{FlatFileTracker, MigrationReport};
+pub use journal::{Change, History, JournalEntry, Journaled, HISTORY_LIMIT};
+pub use jsonl::JsonlTracker;
+pub use listing::{list_entries, EntryFilter, SortBy, UnknownSortBy};
+pub use lock::StorageLock;
+pub use memory::MemoryTracker;
+pub use migration::FORMAT_VERSION;
+pub use origin::{Origin, StaleReason, STALE_SESSION_AGE};
+pub use reporter::{
+  ReportConfig, ReportEntry, ReportTimespan, ReportWindow, Reporter, ReporterError,
+};
+pub use sqlite::SqliteTracker;
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct StartTime(chrono::DateTime<Utc>);
 
 impl StartTime {
   pub fn now() -> Self {
     Self(Utc::now())
   }
+
+  pub fn instant(&self) -> DateTime<Utc> {
+    self.0
+  }
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+impl From<DateTime<Utc>> for StartTime {
+  fn from(instant: DateTime<Utc>) -> Self {
+    Self(instant)
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct EndTime(chrono::DateTime<Utc>);
 
 impl EndTime {
   pub fn now() -> Self {
     Self(Utc::now())
   }
+
+  pub fn instant(&self) -> DateTime<Utc> {
+    self.0
+  }
+}
+
+impl From<DateTime<Utc>> for EndTime {
+  fn from(instant: DateTime<Utc>) -> Self {
+    Self(instant)
+  }
+}
+
+/// Identifies a record. Records written before IDs existed get a new one
+/// when their database is migrated.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct RecordId(Uuid);
+
+impl RecordId {
+  /// Length of the ID prefix shown in listings.
+  pub const SHORT_LEN: usize = 8;
+
+  pub fn new() -> Self {
+    Self(Uuid::new_v4())
+  }
+
+  pub fn is_nil(&self) -> bool {
+    self.0.is_nil()
+  }
+
+  /// The first characters of the ID, enough to tell records apart.
+  pub fn short(&self) -> String {
+    self.0.simple().to_string()[..Self::SHORT_LEN].to_string()
+  }
+
+  /// Whether the ID starts with `prefix`, ignoring case and hyphens.
+  pub fn matches(&self, prefix: &str) -> bool {
+    let prefix = prefix.replace('-', "").to_ascii_lowercase();
+    !prefix.is_empty() && self.0.simple().to_string().starts_with(&prefix)
+  }
+}
+
+impl std::fmt::Display for RecordId {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    self.0.fmt(f)
+  }
+}
+
+impl From<Uuid> for RecordId {
+  fn from(uuid: Uuid) -> Self {
+    Self(uuid)
+  }
+}
+
+/// What the tracked time was spent on. All fields are optional so that
+/// records written before they existed can still be read.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Task {
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub project: Option<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub description: Option<String>,
+  #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+  pub tags: BTreeSet<String>,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+/// A break within a session. Paused time is not tracked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Pause {
+  pub start: DateTime<Utc>,
+  pub end: DateTime<Utc>,
+}
+
+/// The parts of `start..end` that are not paused. `pauses` must be ordered.
+fn active_intervals(start: DateTime<Utc>, end: DateTime<Utc>, pauses: &[Pause]) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+  let mut intervals = Vec::with_capacity(pauses.len() + 1);
+  let mut from = start;
+
+  for pause in pauses {
+    if pause.start > from {
+      intervals.push((from, pause.start.min(end)));
+    }
+    from = from.max(pause.end);
+  }
+  if end > from {
+    intervals.push((from, end));
+  }
+
+  intervals.retain(|(start, end)| end > start);
+  intervals
+}
+
+/// Checks that the pauses are ordered, do not overlap and lie within
+/// `start..end`.
+fn check_pauses(start: DateTime<Utc>, end: DateTime<Utc>, pauses: &[Pause]) -> Result<(), TrackerError> {
+  let mut from = start;
+
+  for pause in pauses {
+    if pause.start < from || pause.end < pause.start || pause.end > end {
+      return Err(Report::new(TrackerError)).attach_printable(format!(
+        "pause from {} to {} is not within the session from {start} to {end}, or overlaps another pause",
+        pause.start, pause.end
+      ));
+    }
+    from = pause.end;
+  }
+
+  Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct TimeRecord {
+  #[serde(default)]
+  pub id: RecordId,
   pub start: StartTime,
   pub end: EndTime,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub pauses: Vec<Pause>,
+  #[serde(flatten)]
+  pub task: Task,
+}
+
+impl TimeRecord {
+  /// Checks that the record ends after it starts, and that its pauses lie
+  /// within it.
+  pub fn validate(&self) -> Result<(), TrackerError> {
+    if self.end.0 <= self.start.0 {
+      return Err(Report::new(TrackerError))
+        .attach_printable(format!("record ends at {} before it starts at {}", self.end.0, self.start.0));
+    }
+
+    check_pauses(self.start.0, self.end.0, &self.pauses)
+  }
+
+  /// The tracked parts of the record, i.e. without its pauses.
+  pub fn active(&self) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+    active_intervals(self.start.0, self.end.0, &self.pauses)
+  }
+
+  /// Whether the two records share any time.
+  pub fn overlaps(&self, other: &TimeRecord) -> bool {
+    self.start.0 < other.end.0 && other.start.0 < self.end.0
+  }
+}
+
+/// Changes to an existing record. Fields left as `None` are kept.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RecordEdit {
+  pub start: Option<StartTime>,
+  pub end: Option<EndTime>,
+  /// `Some(None)` removes the project
+  pub project: Option<Option<String>>,
+  /// `Some(None)` removes the description
+  pub description: Option<Option<String>>,
+  pub add_tags: BTreeSet<String>,
+  pub remove_tags: BTreeSet<String>,
+}
+
+impl RecordEdit {
+  /// The record with the changes applied. The result is not validated.
+  pub fn apply(&self, mut record: TimeRecord) -> TimeRecord {
+    if let Some(start) = self.start {
+      record.start = start;
+    }
+    if let Some(end) = self.end {
+      record.end = end;
+    }
+    if let Some(project) = &self.project {
+      record.task.project = project.clone();
+    }
+    if let Some(description) = &self.description {
+      record.task.description = description.clone();
+    }
+    record.task.tags.extend(self.add_tags.iter().cloned());
+    record.task.tags.retain(|tag| !self.remove_tags.contains(tag));
+
+    record
+  }
+}
+
+/// The session that is tracked while the tracker is running.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Session {
+  pub start: StartTime,
+  pub task: Task,
+  /// Finished pauses, oldest first
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub pauses: Vec<Pause>,
+  /// Start of the current pause, if the session is paused
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub paused_at: Option<DateTime<Utc>>,
+  /// Where the session was started, `None` for sessions started before this
+  /// was recorded
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub origin: Option<Origin>,
+}
+
+impl Session {
+  /// A session started at `start` by this process.
+  pub fn new(start: StartTime, task: Task) -> Self {
+    Session { start, task, pauses: Vec::new(), paused_at: None, origin: Some(Origin::current()) }
+  }
+
+  /// Why the session looks left over, e.g. from a crash or another machine.
+  /// Empty if nothing is suspicious.
+  pub fn stale_reasons(&self, now: DateTime<Utc>) -> Vec<StaleReason> {
+    origin::stale_reasons(self.origin.as_ref(), self.start.0, now, &Origin::current())
+  }
+
+  pub fn is_paused(&self) -> bool {
+    self.paused_at.is_some()
+  }
+
+  /// Pauses the session at `at`. Fails if it is already paused, or if `at` is
+  /// before the session started or before the end of the last pause.
+  pub fn pause(&mut self, at: DateTime<Utc>) -> Result<(), TrackerError> {
+    if self.is_paused() {
+      return Err(Report::new(TrackerError)).attach_printable("session is already paused");
+    }
+
+    let earliest = self.pauses.last().map_or(self.start.0, |pause| pause.end);
+    if at < earliest {
+      return Err(Report::new(TrackerError))
+        .attach_printable(format!("pause time {at} is before {earliest}, when the session was last started or resumed"));
+    }
+
+    self.paused_at = Some(at);
+    Ok(())
+  }
+
+  /// Resumes the session at `at`. Fails if it is not paused, or if `at` is
+  /// before the pause started.
+  pub fn resume(&mut self, at: DateTime<Utc>) -> Result<(), TrackerError> {
+    let start = self.paused_at.ok_or(TrackerError).attach_printable("session is not paused")?;
+    if at < start {
+      return Err(Report::new(TrackerError))
+        .attach_printable(format!("resume time {at} is before the session was paused at {start}"));
+    }
+
+    self.pauses.push(Pause { start, end: at });
+    self.paused_at = None;
+    Ok(())
+  }
+
+  /// The tracked parts of the session up to `now`, i.e. without its pauses.
+  pub fn active(&self, now: DateTime<Utc>) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+    let end = self.paused_at.map_or(now, |paused_at| paused_at.min(now));
+    active_intervals(self.start.0, end, &self.pauses)
+  }
+
+  /// Tracked time of the session up to `now`.
+  pub fn tracked(&self, now: DateTime<Utc>) -> TimeDelta {
+    self.active(now).into_iter().map(|(start, end)| end - start).sum()
+  }
+
+  /// The record of the session stopped at `end`, ending the current pause.
+  /// Fails if `end` is not after the session started, as for
+  /// [`TimeRecord::validate`], or is before it was paused.
+  pub fn finish(mut self, end: EndTime) -> Result<TimeRecord, TrackerError> {
+    if end.0 <= self.start.0 {
+      return Err(Report::new(TrackerError))
+        .attach_printable(format!("stop time {} is not after the start time {}", end.0, self.start.0));
+    }
+    if self.is_paused() {
+      self.resume(end.0).attach_printable("stop time is before the session was paused")?;
+    }
+    check_pauses(self.start.0, end.0, &self.pauses)?;
+
+    Ok(TimeRecord { id: RecordId::new(), start: self.start, end, pauses: self.pauses, task: self.task })
+  }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -45,56 +345,741 @@ pub enum StartupStatus {
 pub struct TrackerError;
 
 pub trait Tracker {
-  fn start(&mut self) -> Result<StartupStatus, TrackerError>;
+  /// Keeps other processes from reading or changing the tracker until the
+  /// returned guard is dropped. Backends that are not shared between
+  /// processes or that lock by themselves return a guard that locks nothing.
+  fn lock_storage(&self) -> Result<StorageLock, TrackerError> {
+    Ok(StorageLock::default())
+  }
+
+  /// Runs `f` without other processes changing the tracker in between, for
+  /// changes made of several steps, see [`Tracker::lock_storage`].
+  fn atomically<R, F>(&mut self, f: F) -> Result<R, TrackerError>
+  where
+    F: FnOnce(&mut Self) -> Result<R, TrackerError>,
+  {
+    let _lock = self.lock_storage()?;
+    f(self)
+  }
+
+  fn start(&mut self, task: Task) -> Result<StartupStatus, TrackerError> {
+    self.start_at(task, StartTime::now())
+  }
+
+  /// Starts tracking as if the tracker had been started at `start`.
+  fn start_at(&mut self, task: Task, start: StartTime) -> Result<StartupStatus, TrackerError>;
 
   fn is_running(&self) -> bool;
 
-  fn stop(&mut self) -> Result<(), TrackerError>;
+  /// The running session, if the tracker is running.
+  fn session(&self) -> Result<Option<Session>, TrackerError>;
 
-  fn has_records(&self) -> bool;
+  fn stop(&mut self) -> Result<TimeRecord, TrackerError> {
+    self.stop_at(EndTime::now())
+  }
 
-  fn records(&self) -> Result<impl Iterator<Item = TimeRecord>, TrackerError>;
-}
+  /// Stops tracking as if the tracker had been stopped at `end`, and returns
+  /// the new record. Fails if `end` is before the running session started.
+  fn stop_at(&mut self, end: EndTime) -> Result<TimeRecord, TrackerError>;
 
-#[cfg(test)]
-pub mod tlib {
-  use super::*;
+  fn switch(&mut self, task: Task) -> Result<Option<TimeRecord>, TrackerError> {
+    self.switch_at(task, StartTime::now())
+  }
+
+  /// Stops the running session and starts a new one for `task` at the same
+  /// instant, in one step. Returns the record of the stopped session, or
+  /// `None` if the tracker was not running and has just been started.
+  fn switch_at(&mut self, task: Task, at: StartTime) -> Result<Option<TimeRecord>, TrackerError>;
+
+  /// Replaces the running session without creating a record, e.g. to undo a
+  /// start or a stop. `None` leaves the tracker stopped.
+  fn restore_session(&mut self, session: Option<Session>) -> Result<(), TrackerError>;
+
+  fn pause(&mut self) -> Result<Session, TrackerError> {
+    self.pause_at(Utc::now())
+  }
+
+  /// Pauses the running session at `at`, and returns the paused session.
+  fn pause_at(&mut self, at: DateTime<Utc>) -> Result<Session, TrackerError> {
+    self.atomically(|tracker| {
+      let mut session = tracker.session()?.ok_or(TrackerError).attach_printable("tracker is not running")?;
+      session.pause(at)?;
+      tracker.restore_session(Some(session.clone()))?;
 
-  #[derive(Debug, Default)]
-  pub struct FakeTracker {
-    tracking: Option<StartTime>,
-    records: Vec<TimeRecord>,
+      Ok(session)
+    })
   }
 
-  impl Tracker for FakeTracker {
-    fn start(&mut self) -> Result<StartupStatus, TrackerError> {
-      if self.tracking.is_some() {
-        Ok(StartupStatus::Running)
-      } else {
-        self.tracking = Some(StartTime::now());
-        Ok(StartupStatus::Started)
+  fn resume(&mut self) -> Result<Session, TrackerError> {
+    self.resume_at(Utc::now())
+  }
+
+  /// Resumes the paused session at `at`, and returns the resumed session.
+  fn resume_at(&mut self, at: DateTime<Utc>) -> Result<Session, TrackerError> {
+    self.atomically(|tracker| {
+      let mut session = tracker.session()?.ok_or(TrackerError).attach_printable("tracker is not running")?;
+      session.resume(at)?;
+      tracker.restore_session(Some(session.clone()))?;
+
+      Ok(session)
+    })
+  }
+
+  /// Discards the running session without creating a record, and returns it.
+  fn cancel(&mut self) -> Result<Session, TrackerError> {
+    self.atomically(|tracker| {
+      let session = tracker.session()?.ok_or(TrackerError).attach_printable("tracker is not running")?;
+      tracker.restore_session(None)?;
+
+      Ok(session)
+    })
+  }
+
+  /// Whether any record is stored. Fails if the records cannot be read.
+  fn has_records(&self) -> Result<bool, TrackerError>;
+
+  fn records(&self) -> Result<impl Iterator<Item = TimeRecord>, TrackerError>;
+
+  /// Inserts a finished record, e.g. a session that was not tracked live.
+  /// Fails if the record is invalid or its ID is taken, and returns the
+  /// existing records that overlap it.
+  fn add(&mut self, record: TimeRecord) -> Result<Vec<TimeRecord>, TrackerError>;
+
+  /// Inserts many finished records, e.g. when moving records between
+  /// backends, and returns how many were inserted. Fails if a record is
+  /// invalid or its ID is taken. Backends that can insert in one step
+  /// insert nothing in that case; this default stops at the failing record.
+  fn import<I>(&mut self, records: I) -> Result<usize, TrackerError>
+  where
+    I: IntoIterator<Item = TimeRecord>,
+  {
+    self.atomically(|tracker| {
+      let mut count = 0;
+      for record in records {
+        tracker.add(record)?;
+        count += 1;
       }
-    }
 
-    fn is_running(&self) -> bool {
-      self.tracking.is_some()
-    }
+      Ok(count)
+    })
+  }
 
-    fn stop(&mut self) -> Result<(), TrackerError> {
-      let start_time = self.tracking.take().unwrap();
-      let end_time = EndTime::now();
+  /// Removes every record, e.g. before importing into a tracker that is
+  /// replaced. The running session is kept, since it may be shared with
+  /// other trackers through the lockfile. Backends that store the records in
+  /// one place clear them in one step; this default deletes them one by one.
+  fn clear(&mut self) -> Result<(), TrackerError> {
+    self.atomically(|tracker| {
+      let ids = tracker.records()?.map(|record| record.id).collect::<Vec<_>>();
+      for id in ids {
+        tracker.delete(id)?;
+      }
 
-      let record = TimeRecord { start: start_time, end: end_time };
-      self.records.push(record);
       Ok(())
-    }
+    })
+  }
 
-    fn has_records(&self) -> bool {
-      !self.records.is_empty()
-    }
+  /// Replaces the stored record that has the same ID. Fails if the record is
+  /// invalid or does not exist, and returns the replaced version.
+  fn replace(&mut self, record: TimeRecord) -> Result<TimeRecord, TrackerError>;
+
+  /// Removes a record and returns it.
+  fn delete(&mut self, id: RecordId) -> Result<TimeRecord, TrackerError>;
+
+  /// Applies `edit` to a record and returns the edited version.
+  fn edit(&mut self, id: RecordId, edit: &RecordEdit) -> Result<TimeRecord, TrackerError> {
+    self.atomically(|tracker| {
+      let record = edit.apply(tracker.find(&id.to_string())?);
+      tracker.replace(record.clone())?;
+
+      Ok(record)
+    })
+  }
 
-    fn records(&self) -> Result<impl Iterator<Item = TimeRecord>, TrackerError> {
-      Ok(self.records.iter().cloned())
+  /// The record whose ID starts with `prefix`, e.g. the short ID shown in
+  /// listings. Fails if no record or more than one record matches.
+  fn find(&self, prefix: &str) -> Result<TimeRecord, TrackerError> {
+    let mut matches = self.records()?.filter(|record| record.id.matches(prefix));
+
+    match (matches.next(), matches.next()) {
+      (Some(record), None) => Ok(record),
+      (None, _) => Err(Report::new(TrackerError)).attach_printable(format!("no record with ID {prefix}")),
+      (Some(_), Some(_)) => Err(Report::new(TrackerError))
+        .attach_printable(format!("more than one record with an ID starting with {prefix}"))
+        .attach(Suggestion("give more characters of the ID")),
     }
   }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+pub mod tlib {
+  /// Generates the test suite that every `Tracker` implementation must pass.
+  ///
+  /// Takes the name of a function `fn(&TempDir) -> impl Tracker` which
+  /// creates a tracker storing its data inside the given directory.
+  macro_rules! tracker_tests {
+    ($new_tracker:ident) => {
+      mod tracker_suite {
+        use assert_fs::TempDir;
+
+        use chrono::{DateTime, Utc};
+
+        use $crate::feature::tracker::{
+          Origin, Pause, RecordEdit, RecordId, Session, StartupStatus, Task, TimeRecord, Tracker,
+        };
+
+        fn utc(s: &str) -> DateTime<Utc> {
+          DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+        }
+
+        fn record(start: &str, end: &str) -> TimeRecord {
+          TimeRecord {
+            id: RecordId::new(),
+            start: utc(start).into(),
+            end: utc(end).into(),
+            pauses: Vec::new(),
+            task: Task::default(),
+          }
+        }
+
+        #[test]
+        fn clearing_keeps_the_session() {
+          let temp = TempDir::new().unwrap();
+          let mut tracker = super::$new_tracker(&temp);
+          tracker
+            .import(vec![
+              record("2026-10-15T09:00:00Z", "2026-10-15T10:00:00Z"),
+              record("2026-10-15T11:00:00Z", "2026-10-15T12:00:00Z"),
+            ])
+            .unwrap();
+          tracker.start(Task::default()).unwrap();
+
+          tracker.clear().unwrap();
+
+          assert!(!tracker.has_records().unwrap());
+          assert_eq!(tracker.records().unwrap().count(), 0);
+          assert!(tracker.is_running());
+        }
+
+        #[test]
+        fn starts_tracking_with_default_tracker() {
+          let temp = TempDir::new().unwrap();
+          // Given a new tracker
+          let mut tracker = super::$new_tracker(&temp);
+
+          // When the tracker is started
+          tracker.start(Task::default()).unwrap();
+
+          // Then the tracker is running
+          assert!(tracker.is_running());
+        }
+
+        #[test]
+        fn is_running_returns_false_after_stopping_tracker() {
+          let temp = TempDir::new().unwrap();
+
+          // Given a new tracker
+          let mut tracker = super::$new_tracker(&temp);
+          // When the tracker is started
+          tracker.start(Task::default()).unwrap();
+          tracker.stop().unwrap();
+
+          // Then the tracker is running
+          assert!(!tracker.is_running());
+        }
+
+        #[test]
+        fn time_record_created_when_tracking_stops() {
+          let temp = TempDir::new().unwrap();
+
+          // Given a new tracker
+          let mut tracker = super::$new_tracker(&temp);
+          // When the tracker is started
+          tracker.start(Task::default()).unwrap();
+
+          std::thread::sleep(std::time::Duration::from_secs(1));
+
+          tracker.stop().unwrap();
+
+          // Then a record is saved
+          assert!(tracker.has_records().unwrap());
+          assert!(tracker.records().unwrap().next().is_some());
+        }
+
+        #[test]
+        fn initial_start_returns_started_state() {
+          let temp = TempDir::new().unwrap();
+
+          // Given a new tracker
+          let mut tracker = super::$new_tracker(&temp);
+          // When the tracker is started
+          let started = tracker.start(Task::default()).unwrap();
+
+          std::thread::sleep(std::time::Duration::from_secs(1));
+
+          // Then the "already running" state is returned
+          assert_eq!(started, StartupStatus::Started);
+        }
+
+        #[test]
+        fn multiple_starts_returns_already_running_state() {
+          let temp = TempDir::new().unwrap();
+
+          // Given a new tracker
+          let mut tracker = super::$new_tracker(&temp);
+          // When the tracker is started
+          tracker.start(Task::default()).unwrap();
+
+          std::thread::sleep(std::time::Duration::from_secs(1));
+
+          let started = tracker.start(Task::default()).unwrap();
+
+          // Then the "already running" state is returned
+          assert_eq!(started, StartupStatus::Running);
+        }
+
+        #[test]
+        fn task_is_kept_on_the_record() {
+          let temp = TempDir::new().unwrap();
+
+          // Given a tracker started with a project, description and tags
+          let mut tracker = super::$new_tracker(&temp);
+          let task = Task {
+            project: Some("website".to_string()),
+            description: Some("fix header".to_string()),
+            tags: ["css".to_string(), "bug".to_string()].into(),
+          };
+          tracker.start(task.clone()).unwrap();
+
+          // When the tracker is stopped
+          tracker.stop().unwrap();
+
+          // Then the record has the same task
+          let record = tracker.records().unwrap().next().unwrap();
+          assert_eq!(record.task, task);
+        }
+
+        #[test]
+        fn session_is_available_while_running() {
+          let temp = TempDir::new().unwrap();
+
+          // Given a new tracker
+          let mut tracker = super::$new_tracker(&temp);
+          assert_eq!(tracker.session().unwrap(), None);
+
+          // When the tracker is started
+          let task = Task {
+            project: Some("website".to_string()),
+            ..Default::default()
+          };
+          tracker.start(task.clone()).unwrap();
+
+          // Then the session is available until the tracker stops
+          assert_eq!(tracker.session().unwrap().unwrap().task, task);
+          tracker.stop().unwrap();
+          assert_eq!(tracker.session().unwrap(), None);
+        }
+
+        #[test]
+        fn added_record_is_stored() {
+          let temp = TempDir::new().unwrap();
+          let mut tracker = super::$new_tracker(&temp);
+
+          let overlaps = tracker
+            .add(record("2026-10-15T09:00:00Z", "2026-10-15T10:30:00Z"))
+            .unwrap();
+
+          assert!(overlaps.is_empty());
+          let stored = tracker.records().unwrap().next().unwrap();
+          assert_eq!(stored.start, record("2026-10-15T09:00:00Z", "2026-10-15T10:30:00Z").start);
+        }
+
+        #[test]
+        fn adding_inverted_record_fails() {
+          let temp = TempDir::new().unwrap();
+          let mut tracker = super::$new_tracker(&temp);
+
+          assert!(tracker.add(record("2026-10-15T10:30:00Z", "2026-10-15T09:00:00Z")).is_err());
+          assert!(!tracker.has_records().unwrap());
+        }
+
+        #[test]
+        fn adding_overlapping_record_returns_overlaps() {
+          let temp = TempDir::new().unwrap();
+          let mut tracker = super::$new_tracker(&temp);
+
+          tracker.add(record("2026-10-15T09:00:00Z", "2026-10-15T10:00:00Z")).unwrap();
+          tracker.add(record("2026-10-15T10:00:00Z", "2026-10-15T11:00:00Z")).unwrap();
+
+          let overlaps = tracker
+            .add(record("2026-10-15T09:30:00Z", "2026-10-15T09:45:00Z"))
+            .unwrap();
+
+          assert_eq!(overlaps.len(), 1);
+          assert_eq!(tracker.records().unwrap().count(), 3);
+        }
+
+        #[test]
+        fn backdated_session_is_recorded() {
+          let temp = TempDir::new().unwrap();
+          let mut tracker = super::$new_tracker(&temp);
+
+          // When the tracker is started and stopped at explicit instants
+          tracker.start_at(Task::default(), utc("2026-10-15T09:00:00Z").into()).unwrap();
+          assert_eq!(tracker.session().unwrap().unwrap().start, utc("2026-10-15T09:00:00Z").into());
+          tracker.stop_at(utc("2026-10-15T10:30:00Z").into()).unwrap();
+
+          // Then the record covers exactly that time
+          let stored = tracker.records().unwrap().next().unwrap();
+          assert_eq!(stored.start, utc("2026-10-15T09:00:00Z").into());
+          assert_eq!(stored.end, utc("2026-10-15T10:30:00Z").into());
+        }
+
+        #[test]
+        fn stopping_before_the_start_fails() {
+          let temp = TempDir::new().unwrap();
+          let mut tracker = super::$new_tracker(&temp);
+          tracker.start_at(Task::default(), utc("2026-10-15T09:00:00Z").into()).unwrap();
+
+          // Then stopping before the session started fails and keeps it running
+          assert!(tracker.stop_at(utc("2026-10-15T08:00:00Z").into()).is_err());
+          assert!(tracker.is_running());
+          assert!(!tracker.has_records().unwrap());
+        }
+
+        #[test]
+        fn stopping_at_the_start_fails() {
+          let temp = TempDir::new().unwrap();
+          let mut tracker = super::$new_tracker(&temp);
+          tracker.start_at(Task::default(), utc("2026-10-15T09:00:00Z").into()).unwrap();
+
+          // Then a session without any tracked time is not stored
+          assert!(tracker.stop_at(utc("2026-10-15T09:00:00Z").into()).is_err());
+          assert!(tracker.switch_at(Task::default(), utc("2026-10-15T09:00:00Z").into()).is_err());
+          assert!(tracker.is_running());
+          assert!(!tracker.has_records().unwrap());
+        }
+
+        #[test]
+        fn records_keep_their_ids() {
+          let temp = TempDir::new().unwrap();
+          let mut tracker = super::$new_tracker(&temp);
+
+          let added = record("2026-10-15T09:00:00Z", "2026-10-15T10:00:00Z");
+          tracker.add(added.clone()).unwrap();
+          tracker.start(Task::default()).unwrap();
+          tracker.stop().unwrap();
+
+          // Then every record has its own ID, which stays the same across reads
+          let ids = tracker.records().unwrap().map(|record| record.id).collect::<Vec<_>>();
+          assert_eq!(ids.len(), 2);
+          assert!(ids.contains(&added.id));
+          assert!(ids.iter().all(|id| !id.is_nil()));
+          assert_ne!(ids[0], ids[1]);
+          assert_eq!(tracker.records().unwrap().map(|record| record.id).collect::<Vec<_>>(), ids);
+        }
+
+        #[test]
+        fn records_are_found_by_id_prefix() {
+          let temp = TempDir::new().unwrap();
+          let mut tracker = super::$new_tracker(&temp);
+
+          let added = record("2026-10-15T09:00:00Z", "2026-10-15T10:00:00Z");
+          tracker.add(added.clone()).unwrap();
+
+          assert_eq!(tracker.find(&added.id.short()).unwrap().id, added.id);
+          assert_eq!(tracker.find(&added.id.to_string()).unwrap().id, added.id);
+          assert!(tracker.find("").is_err());
+          assert!(tracker.find("not-an-id").is_err());
+        }
+
+        #[test]
+        fn adding_a_record_twice_fails() {
+          let temp = TempDir::new().unwrap();
+          let mut tracker = super::$new_tracker(&temp);
+
+          let added = record("2026-10-15T09:00:00Z", "2026-10-15T10:00:00Z");
+          tracker.add(added.clone()).unwrap();
+
+          assert!(tracker.add(added).is_err());
+          assert_eq!(tracker.records().unwrap().count(), 1);
+        }
+
+        #[test]
+        fn imported_records_are_stored() {
+          let temp = TempDir::new().unwrap();
+          let mut tracker = super::$new_tracker(&temp);
+          let existing = record("2026-10-14T09:00:00Z", "2026-10-14T10:00:00Z");
+          tracker.add(existing.clone()).unwrap();
+
+          let imported = vec![
+            record("2026-10-15T09:00:00Z", "2026-10-15T10:00:00Z"),
+            record("2026-10-15T11:00:00Z", "2026-10-15T12:00:00Z"),
+          ];
+          assert_eq!(tracker.import(imported.clone()).unwrap(), 2);
+
+          let mut records = tracker.records().unwrap().collect::<Vec<_>>();
+          records.sort_by_key(|record| record.start);
+          assert_eq!(records, [vec![existing.clone()], imported].concat());
+
+          // A taken ID fails the import
+          assert!(tracker.import(vec![existing]).is_err());
+          assert_eq!(tracker.records().unwrap().count(), 3);
+        }
+
+        #[test]
+        fn edited_record_is_stored() {
+          let temp = TempDir::new().unwrap();
+          let mut tracker = super::$new_tracker(&temp);
+
+          let mut added = record("2026-10-15T09:00:00Z", "2026-10-15T10:00:00Z");
+          added.task.tags.insert("css".to_string());
+          tracker.add(added.clone()).unwrap();
+
+          // When the end, project and tags are changed
+          let edit = RecordEdit {
+            end: Some(utc("2026-10-15T10:30:00Z").into()),
+            project: Some(Some("website".to_string())),
+            add_tags: ["bug".to_string()].into(),
+            remove_tags: ["css".to_string()].into(),
+            ..Default::default()
+          };
+          let edited = tracker.edit(added.id, &edit).unwrap();
+
+          // Then the stored record has the changes and keeps everything else
+          assert_eq!(tracker.records().unwrap().collect::<Vec<_>>(), vec![edited.clone()]);
+          assert_eq!(edited.id, added.id);
+          assert_eq!(edited.start, added.start);
+          assert_eq!(edited.end, utc("2026-10-15T10:30:00Z").into());
+          assert_eq!(edited.task.project.as_deref(), Some("website"));
+          assert_eq!(edited.task.tags, ["bug".to_string()].into());
+        }
+
+        #[test]
+        fn invalid_edit_keeps_the_record() {
+          let temp = TempDir::new().unwrap();
+          let mut tracker = super::$new_tracker(&temp);
+
+          let added = record("2026-10-15T09:00:00Z", "2026-10-15T10:00:00Z");
+          tracker.add(added.clone()).unwrap();
+
+          let edit = RecordEdit {
+            end: Some(utc("2026-10-15T08:00:00Z").into()),
+            ..Default::default()
+          };
+          assert!(tracker.edit(added.id, &edit).is_err());
+          assert_eq!(tracker.records().unwrap().collect::<Vec<_>>(), vec![added]);
+        }
+
+        #[test]
+        fn replacing_a_missing_record_fails() {
+          let temp = TempDir::new().unwrap();
+          let mut tracker = super::$new_tracker(&temp);
+
+          assert!(tracker.replace(record("2026-10-15T09:00:00Z", "2026-10-15T10:00:00Z")).is_err());
+          assert!(!tracker.has_records().unwrap());
+        }
+
+        #[test]
+        fn deleted_record_is_removed() {
+          let temp = TempDir::new().unwrap();
+          let mut tracker = super::$new_tracker(&temp);
+
+          let kept = record("2026-10-15T09:00:00Z", "2026-10-15T10:00:00Z");
+          let deleted = record("2026-10-15T11:00:00Z", "2026-10-15T12:00:00Z");
+          tracker.add(kept.clone()).unwrap();
+          tracker.add(deleted.clone()).unwrap();
+
+          assert_eq!(tracker.delete(deleted.id).unwrap(), deleted);
+          assert_eq!(tracker.records().unwrap().collect::<Vec<_>>(), vec![kept]);
+          assert!(tracker.delete(deleted.id).is_err());
+        }
+
+        #[test]
+        fn stopping_returns_the_new_record() {
+          let temp = TempDir::new().unwrap();
+          let mut tracker = super::$new_tracker(&temp);
+
+          tracker.start_at(Task::default(), utc("2026-10-15T09:00:00Z").into()).unwrap();
+          let stopped = tracker.stop_at(utc("2026-10-15T10:00:00Z").into()).unwrap();
+
+          assert_eq!(tracker.records().unwrap().collect::<Vec<_>>(), vec![stopped]);
+        }
+
+        #[test]
+        fn switching_closes_the_record_and_starts_at_the_same_instant() {
+          let temp = TempDir::new().unwrap();
+          let mut tracker = super::$new_tracker(&temp);
+          let task = Task {
+            project: Some("website".to_string()),
+            ..Default::default()
+          };
+
+          tracker.start_at(Task::default(), utc("2026-10-15T09:00:00Z").into()).unwrap();
+          let switched = tracker.switch_at(task.clone(), utc("2026-10-15T10:00:00Z").into()).unwrap();
+
+          let record = switched.unwrap();
+          assert_eq!(tracker.records().unwrap().collect::<Vec<_>>(), vec![record.clone()]);
+          assert_eq!(record.task, Task::default());
+          let session = tracker.session().unwrap().unwrap();
+          assert_eq!(session.start.0, record.end.0);
+          assert_eq!(session.task, task);
+        }
+
+        #[test]
+        fn switching_a_stopped_tracker_starts_it() {
+          let temp = TempDir::new().unwrap();
+          let mut tracker = super::$new_tracker(&temp);
+
+          let switched = tracker.switch(Task::default()).unwrap();
+
+          assert_eq!(switched, None);
+          assert!(tracker.is_running());
+          assert!(!tracker.has_records().unwrap());
+        }
+
+        #[test]
+        fn switching_before_the_start_keeps_the_session() {
+          let temp = TempDir::new().unwrap();
+          let mut tracker = super::$new_tracker(&temp);
+
+          tracker.start_at(Task::default(), utc("2026-10-15T09:00:00Z").into()).unwrap();
+          let before = tracker.session().unwrap();
+
+          assert!(tracker.switch_at(Task::default(), utc("2026-10-15T08:00:00Z").into()).is_err());
+          assert_eq!(tracker.session().unwrap(), before);
+          assert!(!tracker.has_records().unwrap());
+        }
+
+        #[test]
+        fn session_remembers_where_it_was_started() {
+          let temp = TempDir::new().unwrap();
+          let mut tracker = super::$new_tracker(&temp);
+
+          tracker.start(Task::default()).unwrap();
+          let session = tracker.session().unwrap().unwrap();
+
+          assert_eq!(session.origin, Some(Origin::current()));
+          assert_eq!(session.stale_reasons(Utc::now()), []);
+        }
+
+        #[test]
+        fn restored_session_replaces_the_running_one() {
+          let temp = TempDir::new().unwrap();
+          let mut tracker = super::$new_tracker(&temp);
+
+          // When a paused session is restored on a stopped tracker
+          let session = Session {
+            start: utc("2026-10-15T09:00:00Z").into(),
+            task: Task {
+              project: Some("website".to_string()),
+              ..Default::default()
+            },
+            pauses: vec![Pause {
+              start: utc("2026-10-15T09:30:00Z"),
+              end: utc("2026-10-15T09:45:00Z"),
+            }],
+            paused_at: Some(utc("2026-10-15T10:00:00Z")),
+            origin: None,
+          };
+          tracker.restore_session(Some(session.clone())).unwrap();
+
+          // Then it is running, and removing it again leaves no record behind
+          assert_eq!(tracker.session().unwrap(), Some(session));
+          tracker.restore_session(None).unwrap();
+          assert!(!tracker.is_running());
+          assert!(!tracker.has_records().unwrap());
+          tracker.restore_session(None).unwrap();
+        }
+
+        #[test]
+        fn cancelled_session_leaves_no_record() {
+          let temp = TempDir::new().unwrap();
+          let mut tracker = super::$new_tracker(&temp);
+
+          tracker.add(record("2026-10-15T09:00:00Z", "2026-10-15T10:00:00Z")).unwrap();
+          let task = Task {
+            project: Some("website".to_string()),
+            ..Default::default()
+          };
+          tracker.start(task.clone()).unwrap();
+
+          // When the session is cancelled
+          let cancelled = tracker.cancel().unwrap();
+
+          // Then it is gone and the records are untouched
+          assert_eq!(cancelled.task, task);
+          assert!(!tracker.is_running());
+          assert_eq!(tracker.records().unwrap().count(), 1);
+          assert!(tracker.cancel().is_err());
+        }
+
+        #[test]
+        fn paused_time_is_kept_on_the_record() {
+          let temp = TempDir::new().unwrap();
+          let mut tracker = super::$new_tracker(&temp);
+
+          // Given a session paused twice, the second time until it stops
+          tracker.start_at(Task::default(), utc("2026-10-15T09:00:00Z").into()).unwrap();
+          tracker.pause_at(utc("2026-10-15T09:30:00Z")).unwrap();
+          assert!(tracker.session().unwrap().unwrap().is_paused());
+          tracker.resume_at(utc("2026-10-15T09:45:00Z")).unwrap();
+          tracker.pause_at(utc("2026-10-15T10:00:00Z")).unwrap();
+          tracker.stop_at(utc("2026-10-15T10:30:00Z").into()).unwrap();
+
+          // Then the record holds both pauses
+          let stored = tracker.records().unwrap().next().unwrap();
+          assert_eq!(
+            stored.pauses,
+            vec![
+              Pause { start: utc("2026-10-15T09:30:00Z"), end: utc("2026-10-15T09:45:00Z") },
+              Pause { start: utc("2026-10-15T10:00:00Z"), end: utc("2026-10-15T10:30:00Z") },
+            ]
+          );
+          assert_eq!(
+            stored.active(),
+            vec![
+              (utc("2026-10-15T09:00:00Z"), utc("2026-10-15T09:30:00Z")),
+              (utc("2026-10-15T09:45:00Z"), utc("2026-10-15T10:00:00Z")),
+            ]
+          );
+        }
+
+        #[test]
+        fn invalid_pauses_are_rejected() {
+          let temp = TempDir::new().unwrap();
+          let mut tracker = super::$new_tracker(&temp);
+
+          // Pausing and resuming need a running session
+          assert!(tracker.pause().is_err());
+          tracker.start_at(Task::default(), utc("2026-10-15T09:00:00Z").into()).unwrap();
+          assert!(tracker.resume().is_err());
+
+          // A pause can not start before the session, and not be paused twice
+          assert!(tracker.pause_at(utc("2026-10-15T08:00:00Z")).is_err());
+          tracker.pause_at(utc("2026-10-15T10:00:00Z")).unwrap();
+          assert!(tracker.pause_at(utc("2026-10-15T10:05:00Z")).is_err());
+
+          // Resuming and stopping can not happen before the pause started
+          assert!(tracker.resume_at(utc("2026-10-15T09:30:00Z")).is_err());
+          assert!(tracker.stop_at(utc("2026-10-15T09:30:00Z").into()).is_err());
+          assert!(tracker.is_running());
+        }
+
+        #[test]
+        fn stopping_a_stopped_tracker_fails() {
+          let temp = TempDir::new().unwrap();
+
+          // Given a new tracker
+          let mut tracker = super::$new_tracker(&temp);
+
+          // Then stopping it without starting fails
+          assert!(tracker.stop().is_err());
+          assert!(!tracker.has_records().unwrap());
+        }
+      }
+    };
+  }
+
+  pub(crate) use tracker_tests;
+}
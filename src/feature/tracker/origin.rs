@@ -0,0 +1,147 @@
+use std::fmt;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Sessions running for longer than this are suspected to be left over, e.g.
+/// from a crash or a forgotten stop.
+pub const STALE_SESSION_AGE: TimeDelta = TimeDelta::hours(12);
+
+/// Where a session was started: the machine, the process and the boot of the
+/// machine.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Origin {
+    pub hostname: String,
+    pub pid: u32,
+    /// Changes on every boot. `None` where the system does not provide one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub boot_id: Option<String>,
+}
+
+impl Origin {
+    /// The origin of sessions started by this process.
+    pub fn current() -> Self {
+        Origin {
+            hostname: hostname(),
+            pid: std::process::id(),
+            boot_id: read_trimmed("/proc/sys/kernel/random/boot_id"),
+        }
+    }
+}
+
+/// Why a session started at `start` from `origin` looks stale, seen from
+/// `current` at `now`. Sessions of unknown origin are only judged by their
+/// age. Empty if nothing is suspicious.
+pub fn stale_reasons(
+    origin: Option<&Origin>,
+    start: DateTime<Utc>,
+    now: DateTime<Utc>,
+    current: &Origin,
+) -> Vec<StaleReason> {
+    let mut reasons = Vec::new();
+
+    if let Some(origin) = origin {
+        if origin.hostname != current.hostname {
+            reasons.push(StaleReason::OtherHost(origin.hostname.clone()));
+        } else if let (Some(boot_id), Some(current_boot_id)) = (&origin.boot_id, &current.boot_id) {
+            if boot_id != current_boot_id {
+                reasons.push(StaleReason::Rebooted);
+            }
+        }
+    }
+
+    if now - start > STALE_SESSION_AGE {
+        reasons.push(StaleReason::Old(now - start));
+    }
+
+    reasons
+}
+
+/// Why a running session is suspected to be stale.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StaleReason {
+    /// Started on another machine, e.g. when the lockfile was synced or copied
+    OtherHost(String),
+    /// Started before the machine was last booted, e.g. before a crash
+    Rebooted,
+    /// Running for a long time, see [`STALE_SESSION_AGE`]
+    Old(TimeDelta),
+}
+
+impl fmt::Display for StaleReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StaleReason::OtherHost(hostname) => write!(f, "the session was started on {hostname}"),
+            StaleReason::Rebooted => f.write_str("the machine was restarted since the session started"),
+            StaleReason::Old(age) => write!(f, "the session has been running for {} hours", age.num_hours()),
+        }
+    }
+}
+
+fn hostname() -> String {
+    read_trimmed("/proc/sys/kernel/hostname")
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .or_else(|| std::env::var("COMPUTERNAME").ok())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+fn read_trimmed(path: &str) -> Option<String> {
+    let value = std::fs::read_to_string(path).ok()?;
+    let value = value.trim();
+
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    fn origin(hostname: &str, boot_id: Option<&str>) -> Origin {
+        Origin {
+            hostname: hostname.to_string(),
+            pid: 42,
+            boot_id: boot_id.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn recent_session_from_this_boot_is_not_stale() {
+        let here = origin("laptop", Some("a"));
+
+        let reasons = stale_reasons(Some(&here), utc("2026-10-15T09:00:00Z"), utc("2026-10-15T17:00:00Z"), &here);
+
+        assert_eq!(reasons, []);
+    }
+
+    #[test]
+    fn session_from_another_host_or_boot_is_stale() {
+        let start = utc("2026-10-15T09:00:00Z");
+        let now = utc("2026-10-15T10:00:00Z");
+        let here = origin("laptop", Some("b"));
+
+        let other_host = stale_reasons(Some(&origin("desktop", Some("a"))), start, now, &here);
+        let other_boot = stale_reasons(Some(&origin("laptop", Some("a"))), start, now, &here);
+        let unknown_boot = stale_reasons(Some(&origin("laptop", None)), start, now, &here);
+        let unknown_origin = stale_reasons(None, start, now, &here);
+
+        assert_eq!(other_host, [StaleReason::OtherHost("desktop".to_string())]);
+        assert_eq!(other_boot, [StaleReason::Rebooted]);
+        assert_eq!(unknown_boot, []);
+        assert_eq!(unknown_origin, []);
+    }
+
+    #[test]
+    fn old_session_is_stale() {
+        let here = origin("laptop", Some("a"));
+
+        let reasons = stale_reasons(Some(&here), utc("2026-10-14T09:00:00Z"), utc("2026-10-15T10:00:00Z"), &here);
+        let unknown_origin = stale_reasons(None, utc("2026-10-14T09:00:00Z"), utc("2026-10-15T10:00:00Z"), &here);
+
+        assert_eq!(reasons, [StaleReason::Old(TimeDelta::hours(25))]);
+        assert_eq!(unknown_origin, reasons);
+    }
+}
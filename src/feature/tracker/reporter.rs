@@ -1,13 +1,203 @@
 use std::time::Duration;
 
-use chrono::Utc;
+use chrono::{
+    DateTime, Datelike, LocalResult, Months, NaiveDate, NaiveTime, Offset, TimeDelta, TimeZone,
+    Utc, Weekday,
+};
+use chrono_tz::Tz;
 use error_stack::{ResultExt, Result};
 
-use super::Tracker;
+use super::{
+    breakdown::{self, Breakdown, GroupBy, Segment},
+    EndTime, RecordId, StartTime, Task, Tracker,
+};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ReportTimespan {
+    /// The given duration up to now
     Last(Duration),
+    /// The current calendar day
+    Today,
+    /// The previous calendar day
+    Yesterday,
+    /// The current calendar week
+    ThisWeek,
+    /// The previous calendar week
+    LastWeek,
+    /// The current calendar month
+    ThisMonth,
+    /// From the start of the given day up to now
+    Since(NaiveDate),
+    /// From the start of the first day to the end of the second day
+    Between(NaiveDate, NaiveDate),
+}
+
+/// Settings that determine how timespans map onto the calendar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReportConfig {
+    /// First day of a calendar week
+    pub week_start: Weekday,
+    /// Timezone that calendar days are taken from
+    pub timezone: Tz,
+    /// Count the running session from its start up to now
+    pub include_running: bool,
+}
+
+impl Default for ReportConfig {
+    fn default() -> Self {
+        Self {
+            week_start: Weekday::Mon,
+            timezone: Tz::UTC,
+            include_running: false,
+        }
+    }
+}
+
+/// A resolved timespan. `start` is inclusive, `end` is exclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReportWindow {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+impl ReportWindow {
+    pub fn contains(&self, instant: DateTime<Utc>) -> bool {
+        self.start <= instant && instant < self.end
+    }
+
+    /// How much of the interval from `start` to `end` lies inside the window.
+    pub fn overlap(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> TimeDelta {
+        let start = start.max(self.start);
+        let end = end.min(self.end);
+
+        (end - start).max(TimeDelta::zero())
+    }
+
+    /// How much of the intervals lies inside the window.
+    pub fn overlap_all(&self, intervals: &[(DateTime<Utc>, DateTime<Utc>)]) -> TimeDelta {
+        intervals.iter().map(|&(start, end)| self.overlap(start, end)).sum()
+    }
+}
+
+impl ReportTimespan {
+    /// Resolves the timespan to a window, following calendar days in the
+    /// configured timezone.
+    pub fn window(&self, config: &ReportConfig, now: DateTime<Utc>) -> ReportWindow {
+        let tz = config.timezone;
+        let today = now.with_timezone(&tz).date_naive();
+        let days = |start: NaiveDate, end: NaiveDate| ReportWindow {
+            start: day_start(start, tz),
+            end: day_start(end, tz),
+        };
+
+        match *self {
+            ReportTimespan::Last(duration) => ReportWindow {
+                start: now - duration,
+                end: now,
+            },
+            ReportTimespan::Today => days(today, next_day(today)),
+            ReportTimespan::Yesterday => days(previous_day(today), today),
+            ReportTimespan::ThisWeek => {
+                let start = week_start(today, config.week_start);
+                days(start, start + chrono::Days::new(7))
+            }
+            ReportTimespan::LastWeek => {
+                let end = week_start(today, config.week_start);
+                days(end - chrono::Days::new(7), end)
+            }
+            ReportTimespan::ThisMonth => {
+                let start = today.with_day(1).unwrap_or(today);
+                days(start, start + Months::new(1))
+            }
+            ReportTimespan::Since(date) => ReportWindow {
+                start: day_start(date, tz),
+                end: now,
+            },
+            ReportTimespan::Between(first, last) => days(first, next_day(last)),
+        }
+    }
+}
+
+fn next_day(date: NaiveDate) -> NaiveDate {
+    date.succ_opt().unwrap_or(date)
+}
+
+fn previous_day(date: NaiveDate) -> NaiveDate {
+    date.pred_opt().unwrap_or(date)
+}
+
+/// The first day of the week containing `date`.
+fn week_start(date: NaiveDate, first_day: Weekday) -> NaiveDate {
+    let offset = (7 + date.weekday().num_days_from_monday() - first_day.num_days_from_monday()) % 7;
+    date - chrono::Days::new(offset.into())
+}
+
+/// The instant the given calendar day starts in `tz`.
+pub(crate) fn day_start(date: NaiveDate, tz: Tz) -> DateTime<Utc> {
+    let midnight = date.and_time(NaiveTime::MIN);
+
+    match tz.from_local_datetime(&midnight) {
+        LocalResult::Single(start) => start.with_timezone(&Utc),
+        LocalResult::Ambiguous(earliest, _) => earliest.with_timezone(&Utc),
+        LocalResult::None => {
+            // Midnight was skipped by a DST transition, so the day starts at
+            // the transition: midnight in the offset that was in effect before.
+            let before = tz
+                .offset_from_utc_datetime(&(midnight - TimeDelta::days(1)))
+                .fix()
+                .local_minus_utc();
+            (midnight - TimeDelta::seconds(before.into())).and_utc()
+        }
+    }
+}
+
+/// A record, or the running session, that overlaps a report window.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReportEntry {
+    /// `None` for the running session
+    pub id: Option<RecordId>,
+    pub start: StartTime,
+    /// `None` for the running session
+    pub end: Option<EndTime>,
+    /// Time inside the report window
+    pub duration: Duration,
+    pub task: Task,
+}
+
+/// Time of the running session inside the window, if the config includes it.
+pub(crate) fn running_time<T>(
+    tracker: &T,
+    window: &ReportWindow,
+    config: &ReportConfig,
+    now: DateTime<Utc>,
+) -> Result<TimeDelta, ReporterError>
+where
+    T: Tracker + ?Sized,
+{
+    if !config.include_running {
+        return Ok(TimeDelta::zero());
+    }
+
+    let session = tracker
+        .session()
+        .change_context(ReporterError)
+        .attach_printable("failed to query running session")?;
+
+    Ok(session
+        .map(|session| window.overlap_all(&session.active(now)))
+        .unwrap_or_default())
+}
+
+/// The parts of the tracked `intervals` inside the window, as segments.
+fn segments(
+    window: &ReportWindow,
+    intervals: Vec<(DateTime<Utc>, DateTime<Utc>)>,
+    task: &Task,
+) -> Vec<Segment> {
+    intervals
+        .into_iter()
+        .filter_map(|(start, end)| Segment::clipped(window, start, end, task.clone()))
+        .collect()
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -15,27 +205,109 @@ pub enum ReportTimespan {
 pub struct ReporterError;
 
 pub trait Reporter: Tracker {
-    fn total_duration(&self, timespan: ReportTimespan) -> Result<Duration, ReporterError> {
-        match timespan {
-            ReportTimespan::Last(duration) => {
-                let now = Utc::now();
-                let target = (now - duration).timestamp_millis();
-                let total_ms = self
-                    .records()
-                    .change_context(ReporterError)
-                    .attach_printable("failed to query records")?
-                    .filter_map(|rec| {
-                        if rec.start.0.timestamp_millis() >= target {
-                            Some(rec.end.0.timestamp_millis() - rec.start.0.timestamp_millis())
-                        } else {
-                            None
-                        }
-                    })
-                    .sum::<i64>();
-
-                Ok(Duration::from_millis(total_ms as u64))
-            },
+    fn total_duration(
+        &self,
+        timespan: ReportTimespan,
+        config: &ReportConfig,
+    ) -> Result<Duration, ReporterError> {
+        let now = Utc::now();
+        let window = timespan.window(config, now);
+        let recorded = self
+            .records()
+            .change_context(ReporterError)
+            .attach_printable("failed to query records")?
+            .map(|rec| window.overlap_all(&rec.active()))
+            .sum::<TimeDelta>();
+
+        let running = running_time(self, &window, config, now)?;
+
+        (recorded + running)
+            .to_std()
+            .change_context(ReporterError)
+            .attach_printable("total duration is negative")
+    }
+
+    /// Tracked time in the timespan, grouped by each level of `group_by` in
+    /// turn. Records are split where they cross calendar buckets.
+    fn breakdown(
+        &self,
+        timespan: ReportTimespan,
+        group_by: &[GroupBy],
+        config: &ReportConfig,
+    ) -> Result<Breakdown, ReporterError> {
+        let now = Utc::now();
+        let window = timespan.window(config, now);
+
+        let mut segments = self
+            .records()
+            .change_context(ReporterError)
+            .attach_printable("failed to query records")?
+            .flat_map(|rec| segments(&window, rec.active(), &rec.task))
+            .collect::<Vec<_>>();
+
+        if config.include_running {
+            let session = self
+                .session()
+                .change_context(ReporterError)
+                .attach_printable("failed to query running session")?;
+
+            if let Some(session) = session {
+                segments.extend(self::segments(&window, session.active(now), &session.task));
+            }
         }
+
+        Ok(breakdown::breakdown(segments, group_by, config))
+    }
+
+    /// Records in the timespan, and the running session if the config
+    /// includes it, ordered by start time.
+    fn entries(
+        &self,
+        timespan: ReportTimespan,
+        config: &ReportConfig,
+    ) -> Result<Vec<ReportEntry>, ReporterError> {
+        let now = Utc::now();
+        let window = timespan.window(config, now);
+
+        let mut entries = self
+            .records()
+            .change_context(ReporterError)
+            .attach_printable("failed to query records")?
+            .filter_map(|rec| {
+                let duration = window.overlap_all(&rec.active()).to_std().ok()?;
+                (duration > Duration::ZERO).then_some(ReportEntry {
+                    id: Some(rec.id),
+                    start: rec.start,
+                    end: Some(rec.end),
+                    duration,
+                    task: rec.task,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        if config.include_running {
+            let session = self
+                .session()
+                .change_context(ReporterError)
+                .attach_printable("failed to query running session")?;
+
+            if let Some(session) = session {
+                let duration = window.overlap_all(&session.active(now)).to_std().unwrap_or_default();
+                if duration > Duration::ZERO {
+                    entries.push(ReportEntry {
+                        id: None,
+                        start: session.start,
+                        end: None,
+                        duration,
+                        task: session.task,
+                    });
+                }
+            }
+        }
+
+        entries.sort_by_key(|entry| entry.start);
+
+        Ok(entries)
     }
 }
 
@@ -43,20 +315,16 @@ pub trait Reporter: Tracker {
 mod tests {
     use std::time::Duration;
 
-    use crate::feature::tracker::tlib::FakeTracker;
+    use crate::feature::tracker::{MemoryTracker, Pause, RecordId, TimeRecord};
 
     use super::*;
 
-    impl Reporter for FakeTracker {
-      
-    }
-
     #[test]
     fn calculate_correct_duration_when_there_are_no_records() {
-        let tracker = FakeTracker::default();
+        let tracker = MemoryTracker::default();
 
         let duration = tracker
-            .total_duration(ReportTimespan::Last(Duration::from_secs(1)))
+            .total_duration(ReportTimespan::Last(Duration::from_secs(1)), &ReportConfig::default())
             .unwrap();
 
         assert_eq!(duration, Duration::from_millis(0));
@@ -64,20 +332,316 @@ mod tests {
 
     #[test]
     fn calculate_correct_duration_when_there_are_two_records() {
-        let mut tracker = FakeTracker::default();
+        let mut tracker = MemoryTracker::default();
 
-        tracker.start().unwrap();
+        tracker.start(Task::default()).unwrap();
         std::thread::sleep(Duration::from_millis(10));
         tracker.stop().unwrap();
 
-        tracker.start().unwrap();
+        tracker.start(Task::default()).unwrap();
         std::thread::sleep(Duration::from_millis(10));
         tracker.stop().unwrap();
 
         let duration = tracker
-            .total_duration(ReportTimespan::Last(Duration::from_secs(1)))
+            .total_duration(ReportTimespan::Last(Duration::from_secs(1)), &ReportConfig::default())
             .unwrap();
 
         assert!(duration >= Duration::from_millis(20));
     }
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn last_ends_now() {
+        let now = utc("2026-10-15T12:00:00Z");
+        let window = ReportTimespan::Last(Duration::from_secs(60)).window(&ReportConfig::default(), now);
+
+        assert_eq!(window.start, utc("2026-10-15T11:59:00Z"));
+        assert_eq!(window.end, now);
+    }
+
+    #[test]
+    fn yesterday_ends_when_today_starts() {
+        let now = utc("2026-10-15T12:00:00Z");
+        let config = ReportConfig::default();
+
+        let today = ReportTimespan::Today.window(&config, now);
+        let yesterday = ReportTimespan::Yesterday.window(&config, now);
+
+        assert!(today.contains(now));
+        assert_eq!(yesterday.end, today.start);
+        assert!(yesterday.start < yesterday.end);
+    }
+
+    #[test]
+    fn week_starts_on_configured_day() {
+        // A Thursday
+        let now = utc("2026-10-15T12:00:00Z");
+
+        for week_start in [Weekday::Mon, Weekday::Sun, Weekday::Thu, Weekday::Fri] {
+            let config = ReportConfig {
+                week_start,
+                ..Default::default()
+            };
+
+            let this_week = ReportTimespan::ThisWeek.window(&config, now);
+            let last_week = ReportTimespan::LastWeek.window(&config, now);
+
+            let first_day = this_week.start.date_naive();
+            assert_eq!(first_day.weekday(), week_start);
+            assert!(this_week.contains(now));
+            assert_eq!(last_week.end, this_week.start);
+        }
+    }
+
+    #[test]
+    fn month_starts_on_first_day() {
+        let now = utc("2026-10-15T12:00:00Z");
+        let window = ReportTimespan::ThisMonth.window(&ReportConfig::default(), now);
+
+        let first = window.start.date_naive();
+        let next = window.end.date_naive();
+        assert_eq!(first, NaiveDate::from_ymd_opt(2026, 10, 1).unwrap());
+        assert_eq!(next, NaiveDate::from_ymd_opt(2026, 11, 1).unwrap());
+    }
+
+    #[test]
+    fn between_includes_last_day() {
+        let first = NaiveDate::from_ymd_opt(2026, 10, 1).unwrap();
+        let last = NaiveDate::from_ymd_opt(2026, 10, 15).unwrap();
+        let now = utc("2026-10-18T12:00:00Z");
+
+        let window = ReportTimespan::Between(first, last).window(&ReportConfig::default(), now);
+
+        assert_eq!(window.start, utc("2026-10-01T00:00:00Z"));
+        assert_eq!(window.end, utc("2026-10-16T00:00:00Z"));
+    }
+
+    fn in_zone(timezone: Tz) -> ReportConfig {
+        ReportConfig {
+            timezone,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn today_follows_configured_timezone() {
+        // Already the next day in Tokyo
+        let now = utc("2026-10-15T20:00:00Z");
+
+        let window = ReportTimespan::Today.window(&in_zone(Tz::Asia__Tokyo), now);
+
+        assert_eq!(window.start, utc("2026-10-15T15:00:00Z"));
+        assert_eq!(window.end, utc("2026-10-16T15:00:00Z"));
+    }
+
+    #[test]
+    fn spring_forward_day_is_23_hours() {
+        let now = utc("2026-03-08T17:00:00Z");
+
+        let window = ReportTimespan::Today.window(&in_zone(Tz::America__New_York), now);
+
+        assert_eq!(window.start, utc("2026-03-08T05:00:00Z"));
+        assert_eq!(window.end, utc("2026-03-09T04:00:00Z"));
+        assert_eq!(window.end - window.start, TimeDelta::hours(23));
+    }
+
+    #[test]
+    fn fall_back_day_is_25_hours() {
+        let now = utc("2026-11-01T17:00:00Z");
+
+        let window = ReportTimespan::Today.window(&in_zone(Tz::America__New_York), now);
+
+        assert_eq!(window.start, utc("2026-11-01T04:00:00Z"));
+        assert_eq!(window.end, utc("2026-11-02T05:00:00Z"));
+        assert_eq!(window.end - window.start, TimeDelta::hours(25));
+    }
+
+    #[test]
+    fn day_starts_at_transition_when_midnight_is_skipped() {
+        // Havana skips from 00:00 to 01:00 when DST starts
+        let now = utc("2026-03-08T17:00:00Z");
+
+        let window = ReportTimespan::Today.window(&in_zone(Tz::America__Havana), now);
+
+        assert_eq!(window.start, utc("2026-03-08T05:00:00Z"));
+        assert_eq!(window.end - window.start, TimeDelta::hours(23));
+    }
+
+    #[test]
+    fn week_spanning_dst_change_starts_at_local_midnight() {
+        // Thursday before the fall back in Berlin
+        let now = utc("2026-10-22T12:00:00Z");
+
+        let window = ReportTimespan::ThisWeek.window(&in_zone(Tz::Europe__Berlin), now);
+
+        // Monday 00:00 CEST (+02:00) to Monday 00:00 CET (+01:00)
+        assert_eq!(window.start, utc("2026-10-18T22:00:00Z"));
+        assert_eq!(window.end, utc("2026-10-25T23:00:00Z"));
+    }
+
+    fn record(start: &str, end: &str) -> TimeRecord {
+        TimeRecord {
+            id: RecordId::new(),
+            start: StartTime(utc(start)),
+            end: EndTime(utc(end)),
+            pauses: Vec::new(),
+            task: Task::default(),
+        }
+    }
+
+    #[test]
+    fn running_session_is_counted_only_when_included() {
+        let mut tracker = MemoryTracker::default();
+        tracker.start(Task::default()).unwrap();
+        std::thread::sleep(Duration::from_millis(10));
+
+        let timespan = ReportTimespan::Last(Duration::from_secs(60));
+        let excluded = tracker
+            .total_duration(timespan, &ReportConfig::default())
+            .unwrap();
+        let included = tracker
+            .total_duration(
+                timespan,
+                &ReportConfig {
+                    include_running: true,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        assert_eq!(excluded, Duration::ZERO);
+        assert!(included >= Duration::from_millis(10));
+    }
+
+    #[test]
+    fn breakdown_totals_match_total_duration() {
+        let tracker = MemoryTracker::with_records(vec![
+            record("2026-10-14T23:00:00Z", "2026-10-15T01:00:00Z"),
+            record("2026-10-15T10:00:00Z", "2026-10-15T11:00:00Z"),
+            record("2026-10-15T23:30:00Z", "2026-10-16T02:00:00Z"),
+        ]);
+        let first = NaiveDate::from_ymd_opt(2026, 10, 14).unwrap();
+        let last = NaiveDate::from_ymd_opt(2026, 10, 16).unwrap();
+        let timespan = ReportTimespan::Between(first, last);
+        let config = ReportConfig::default();
+
+        let total = tracker.total_duration(timespan, &config).unwrap();
+        let breakdown = tracker.breakdown(timespan, &[GroupBy::Day], &config).unwrap();
+
+        assert_eq!(breakdown.total, total);
+        let days = breakdown
+            .groups
+            .iter()
+            .map(|group| group.breakdown.total.as_secs() / 60)
+            .collect::<Vec<_>>();
+        assert_eq!(days, [60, 150, 120]);
+    }
+
+    #[test]
+    fn entries_hold_records_overlapping_the_window() {
+        let tracker = MemoryTracker::with_records(vec![
+            record("2026-10-14T10:00:00Z", "2026-10-14T11:00:00Z"),
+            record("2026-10-14T23:00:00Z", "2026-10-15T01:00:00Z"),
+            record("2026-10-15T10:00:00Z", "2026-10-15T11:00:00Z"),
+        ]);
+        let day = NaiveDate::from_ymd_opt(2026, 10, 15).unwrap();
+
+        let entries = tracker
+            .entries(ReportTimespan::Between(day, day), &ReportConfig::default())
+            .unwrap();
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].start.instant(), utc("2026-10-14T23:00:00Z"));
+        assert_eq!(entries[0].duration, Duration::from_secs(3600));
+    }
+
+    #[test]
+    fn overlap_clips_interval_to_window() {
+        let window = ReportWindow {
+            start: utc("2026-10-15T00:00:00Z"),
+            end: utc("2026-10-16T00:00:00Z"),
+        };
+
+        // Straddles the window start
+        let before = window.overlap(utc("2026-10-14T23:00:00Z"), utc("2026-10-15T01:00:00Z"));
+        // Straddles the window end
+        let after = window.overlap(utc("2026-10-15T23:30:00Z"), utc("2026-10-16T02:00:00Z"));
+        // Covers the whole window
+        let around = window.overlap(utc("2026-10-14T00:00:00Z"), utc("2026-10-17T00:00:00Z"));
+        // Outside the window
+        let outside = window.overlap(utc("2026-10-16T01:00:00Z"), utc("2026-10-16T02:00:00Z"));
+
+        assert_eq!(before, TimeDelta::hours(1));
+        assert_eq!(after, TimeDelta::minutes(30));
+        assert_eq!(around, TimeDelta::hours(24));
+        assert_eq!(outside, TimeDelta::zero());
+    }
+
+    #[test]
+    fn total_duration_counts_only_the_part_inside_the_window() {
+        let tracker = MemoryTracker::with_records(vec![
+            record("2026-10-14T23:00:00Z", "2026-10-15T01:00:00Z"),
+            record("2026-10-15T10:00:00Z", "2026-10-15T11:00:00Z"),
+            record("2026-10-15T23:30:00Z", "2026-10-16T02:00:00Z"),
+        ]);
+        let first = NaiveDate::from_ymd_opt(2026, 10, 15).unwrap();
+
+        let duration = tracker
+            .total_duration(ReportTimespan::Between(first, first), &ReportConfig::default())
+            .unwrap();
+
+        assert_eq!(duration, Duration::from_secs(150 * 60));
+    }
+
+    #[test]
+    fn paused_time_is_not_reported() {
+        let mut paused = record("2026-10-15T09:00:00Z", "2026-10-15T12:00:00Z");
+        paused.pauses.push(Pause {
+            start: utc("2026-10-15T10:00:00Z"),
+            end: utc("2026-10-15T10:30:00Z"),
+        });
+        // The pause straddles midnight, only its part inside the window is left out
+        let mut overnight = record("2026-10-15T23:00:00Z", "2026-10-16T01:00:00Z");
+        overnight.pauses.push(Pause {
+            start: utc("2026-10-15T23:30:00Z"),
+            end: utc("2026-10-16T00:30:00Z"),
+        });
+        let tracker = MemoryTracker::with_records(vec![paused, overnight]);
+        let day = NaiveDate::from_ymd_opt(2026, 10, 15).unwrap();
+        let timespan = ReportTimespan::Between(day, day);
+        let config = ReportConfig::default();
+
+        let total = tracker.total_duration(timespan, &config).unwrap();
+        let breakdown = tracker.breakdown(timespan, &[GroupBy::Day], &config).unwrap();
+        let entries = tracker.entries(timespan, &config).unwrap();
+
+        assert_eq!(total, Duration::from_secs(180 * 60));
+        assert_eq!(breakdown.total, total);
+        assert_eq!(entries[0].duration, Duration::from_secs(150 * 60));
+        assert_eq!(entries[1].duration, Duration::from_secs(30 * 60));
+    }
+
+    #[test]
+    fn paused_session_stops_counting() {
+        let mut tracker = MemoryTracker::default();
+        tracker
+            .start_at(Task::default(), StartTime(Utc::now() - TimeDelta::minutes(30)))
+            .unwrap();
+        tracker.pause_at(Utc::now() - TimeDelta::minutes(20)).unwrap();
+
+        let duration = tracker
+            .total_duration(
+                ReportTimespan::Last(Duration::from_secs(3600)),
+                &ReportConfig {
+                    include_running: true,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        assert_eq!(duration.as_secs() / 60, 10);
+    }
 }
@@ -1,38 +1,937 @@
-use std::path::PathBuf;
+// sqlite tracker
+// 1 file:
+// - "records" table: all records
+// - "session" table: at most one row, present while the tracker is running
 
-use super::{Reporter, Tracker};
+use std::{path::PathBuf, time::Duration};
+
+use chrono::{DateTime, SubsecRound, TimeDelta, Utc};
+use error_stack::{Result, ResultExt};
+use rusqlite::{params, types::Type, Connection, OptionalExtension, Row, TransactionBehavior};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::{
+    reporter::running_time, EndTime, Origin, Pause, RecordId, ReportConfig, ReportTimespan, Reporter, ReporterError, Session, StartTime, StartupStatus, Task, TimeRecord,
+    Tracker, TrackerError,
+};
+
+/// SQL expression for a random (version 4) UUID.
+macro_rules! random_uuid {
+    () => {
+        "lower(
+            hex(randomblob(4)) || '-' || hex(randomblob(2)) || '-4' || substr(hex(randomblob(2)), 2)
+            || '-' || substr('89ab', 1 + abs(random()) % 4, 1) || substr(hex(randomblob(2)), 2)
+            || '-' || hex(randomblob(6))
+        )"
+    };
+}
+
+/// Schema migrations, applied in order. The number of applied migrations is
+/// stored in the `user_version` pragma.
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE records (
+        start_ms INTEGER NOT NULL,
+        end_ms INTEGER NOT NULL
+    );
+    CREATE INDEX records_start_ms ON records (start_ms);
+    CREATE TABLE session (
+        id INTEGER PRIMARY KEY CHECK (id = 0),
+        start_ms INTEGER NOT NULL
+    );",
+    "ALTER TABLE records ADD COLUMN project TEXT;
+    ALTER TABLE records ADD COLUMN description TEXT;
+    ALTER TABLE records ADD COLUMN tags TEXT NOT NULL DEFAULT '[]';
+    ALTER TABLE session ADD COLUMN project TEXT;
+    ALTER TABLE session ADD COLUMN description TEXT;
+    ALTER TABLE session ADD COLUMN tags TEXT NOT NULL DEFAULT '[]';",
+    // Existing records get a random (version 4) UUID
+    concat!(
+        "ALTER TABLE records ADD COLUMN id TEXT;
+        UPDATE records SET id = ",
+        random_uuid!(),
+        ";
+        CREATE UNIQUE INDEX records_id ON records (id);"
+    ),
+    "ALTER TABLE records ADD COLUMN pauses TEXT NOT NULL DEFAULT '[]';
+    ALTER TABLE session ADD COLUMN pauses TEXT NOT NULL DEFAULT '[]';
+    ALTER TABLE session ADD COLUMN paused_at_ms INTEGER;",
+    "ALTER TABLE session ADD COLUMN origin TEXT;",
+    // Columns cannot be made `NOT NULL` in place, so the table is rebuilt.
+    // Records inserted without an ID since they were added get one first.
+    concat!(
+        "UPDATE records SET id = ",
+        random_uuid!(),
+        " WHERE id IS NULL;
+        CREATE TABLE records_with_ids (
+            id TEXT NOT NULL UNIQUE,
+            start_ms INTEGER NOT NULL,
+            end_ms INTEGER NOT NULL,
+            project TEXT,
+            description TEXT,
+            tags TEXT NOT NULL DEFAULT '[]',
+            pauses TEXT NOT NULL DEFAULT '[]'
+        );
+        INSERT INTO records_with_ids (id, start_ms, end_ms, project, description, tags, pauses)
+        SELECT id, start_ms, end_ms, project, description, tags, pauses FROM records;
+        DROP TABLE records;
+        ALTER TABLE records_with_ids RENAME TO records;
+        CREATE INDEX records_start_ms ON records (start_ms);"
+    ),
+];
+
+/// Columns read by `session_from_row`.
+const SESSION_COLUMNS: &str = "start_ms, project, description, tags, pauses, paused_at_ms, origin";
+
+/// A pause as stored in the `pauses` JSON columns. Milliseconds let SQL
+/// queries subtract paused time.
+#[derive(Debug, Serialize, Deserialize)]
+struct PauseRow {
+    start_ms: i64,
+    end_ms: i64,
+}
 
 pub struct SqliteTracker {
     database: PathBuf,
-    lockfile: PathBuf,
 }
 
-// impl Tracker for SqliteTracker {
-//     fn start(&mut self) -> error_stack::Result<super::StartupStatus, super::TrackerError> {
-//         todo!("lockfile")
-//     }
+impl SqliteTracker {
+    pub fn new<D>(database: D) -> Self
+    where
+        D: Into<PathBuf>,
+    {
+        let database = database.into();
+
+        SqliteTracker { database }
+    }
+
+    fn connect(&self) -> Result<Connection, TrackerError> {
+        let mut conn = Connection::open(&self.database)
+            .change_context(TrackerError)
+            .attach_printable("unable to open sqlite database")?;
+
+        migrate(&mut conn)
+            .change_context(TrackerError)
+            .attach_printable("unable to migrate sqlite database")?;
+
+        Ok(conn)
+    }
+
+    /// Reads the session and replaces it with the one returned by `f` in a
+    /// single transaction, so that no other process changes it in between.
+    fn update_session<R, F>(&self, action: &str, f: F) -> Result<R, TrackerError>
+    where
+        F: FnOnce(Session) -> Result<(R, Option<Session>), TrackerError>,
+    {
+        let mut conn = self.connect()?;
+        let tx = conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .change_context(TrackerError)
+            .attach_printable_lazy(|| format!("unable to begin transaction when {action}"))?;
+
+        let session = select_session(&tx)
+            .attach_printable_lazy(|| format!("unable to read session when {action}"))?
+            .ok_or(TrackerError)
+            .attach_printable("tracker is not running")?;
+
+        let (result, session) = f(session)?;
+
+        tx.execute("DELETE FROM session", [])
+            .change_context(TrackerError)
+            .attach_printable_lazy(|| format!("unable to remove session when {action}"))?;
+        if let Some(session) = session {
+            insert_session(&tx, &session).attach_printable_lazy(|| format!("unable to insert session when {action}"))?;
+        }
+
+        tx.commit()
+            .change_context(TrackerError)
+            .attach_printable_lazy(|| format!("unable to commit transaction when {action}"))?;
+
+        Ok(result)
+    }
+
+    fn query_running(&self) -> Result<bool, TrackerError> {
+        self.connect()?
+            .query_row("SELECT EXISTS (SELECT 1 FROM session)", [], |row| row.get(0))
+            .change_context(TrackerError)
+            .attach_printable("unable to query session")
+    }
+
+    fn query_has_records(&self) -> Result<bool, TrackerError> {
+        self.connect()?
+            .query_row("SELECT EXISTS (SELECT 1 FROM records)", [], |row| row.get(0))
+            .change_context(TrackerError)
+            .attach_printable("unable to query records")
+    }
+}
+
+impl Tracker for SqliteTracker {
+    fn start_at(&mut self, task: Task, start_time: StartTime) -> Result<StartupStatus, TrackerError> {
+        let session = Session::new(start_time, task);
+
+        let inserted = self
+            .connect()?
+            .execute(
+                "INSERT OR IGNORE INTO session (id, start_ms, project, description, tags, origin)
+                VALUES (0, ?1, ?2, ?3, ?4, ?5)",
+                params![
+                    session.start.0.timestamp_millis(),
+                    session.task.project,
+                    session.task.description,
+                    tags_to_sql(&session.task)?,
+                    origin_to_sql(session.origin.as_ref())?
+                ],
+            )
+            .change_context(TrackerError)
+            .attach_printable("unable to insert session when starting tracker")?;
+
+        if inserted == 0 {
+            Ok(StartupStatus::Running)
+        } else {
+            Ok(StartupStatus::Started)
+        }
+    }
+
+    fn is_running(&self) -> bool {
+        self.query_running().unwrap_or(false)
+    }
+
+    fn session(&self) -> Result<Option<Session>, TrackerError> {
+        select_session(&self.connect()?).attach_printable("unable to read session")
+    }
+
+    fn stop_at(&mut self, end: EndTime) -> Result<TimeRecord, TrackerError> {
+        let end = EndTime(stored_precision(end.0));
+        let mut conn = self.connect()?;
+        let tx = conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .change_context(TrackerError)
+            .attach_printable("unable to begin transaction when stopping tracker")?;
+
+        let session = select_session(&tx)
+            .attach_printable("unable to read session when stopping tracker")?
+            .ok_or(TrackerError)
+            .attach_printable("tracker is not running")?;
+
+        let record = session.finish(end)?;
+
+        insert_record(&tx, &record)
+            .attach_printable("unable to insert record when stopping tracker")?;
+
+        tx.execute("DELETE FROM session", [])
+            .change_context(TrackerError)
+            .attach_printable("unable to remove session when stopping tracker")?;
+
+        tx.commit()
+            .change_context(TrackerError)
+            .attach_printable("unable to commit transaction when stopping tracker")?;
+
+        Ok(record)
+    }
+
+    fn switch_at(&mut self, task: Task, at: StartTime) -> Result<Option<TimeRecord>, TrackerError> {
+        let at = StartTime(stored_precision(at.0));
+        let mut conn = self.connect()?;
+        let tx = conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .change_context(TrackerError)
+            .attach_printable("unable to begin transaction when switching task")?;
+
+        let record = select_session(&tx)
+            .attach_printable("unable to read session when switching task")?
+            .map(|session| session.finish(at.0.into()))
+            .transpose()?;
+
+        if let Some(record) = &record {
+            insert_record(&tx, record).attach_printable("unable to insert record when switching task")?;
+        }
+
+        tx.execute("DELETE FROM session", [])
+            .change_context(TrackerError)
+            .attach_printable("unable to remove session when switching task")?;
+        insert_session(&tx, &Session::new(at, task))
+            .attach_printable("unable to insert session when switching task")?;
+
+        tx.commit()
+            .change_context(TrackerError)
+            .attach_printable("unable to commit transaction when switching task")?;
+
+        Ok(record)
+    }
+
+    fn restore_session(&mut self, session: Option<Session>) -> Result<(), TrackerError> {
+        let mut conn = self.connect()?;
+        let tx = conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .change_context(TrackerError)
+            .attach_printable("unable to begin transaction when restoring session")?;
+
+        tx.execute("DELETE FROM session", [])
+            .change_context(TrackerError)
+            .attach_printable("unable to remove session when restoring session")?;
+
+        if let Some(session) = session {
+            insert_session(&tx, &session).attach_printable("unable to insert session when restoring session")?;
+        }
+
+        tx.commit()
+            .change_context(TrackerError)
+            .attach_printable("unable to commit transaction when restoring session")
+    }
+
+    fn pause_at(&mut self, at: DateTime<Utc>) -> Result<Session, TrackerError> {
+        self.update_session("pausing tracker", |mut session| {
+            session.pause(stored_precision(at))?;
+            Ok((session.clone(), Some(session)))
+        })
+    }
+
+    fn resume_at(&mut self, at: DateTime<Utc>) -> Result<Session, TrackerError> {
+        self.update_session("resuming tracker", |mut session| {
+            session.resume(stored_precision(at))?;
+            Ok((session.clone(), Some(session)))
+        })
+    }
+
+    fn cancel(&mut self) -> Result<Session, TrackerError> {
+        self.update_session("cancelling session", |session| Ok((session, None)))
+    }
+
+    fn has_records(&self) -> Result<bool, TrackerError> {
+        self.query_has_records()
+    }
+
+    fn records(&self) -> Result<impl Iterator<Item = TimeRecord>, TrackerError> {
+        let conn = self.connect()?;
+        let records = select_records(&conn, "ORDER BY start_ms", [])?;
+
+        Ok(records.into_iter())
+    }
+
+    fn add(&mut self, record: TimeRecord) -> Result<Vec<TimeRecord>, TrackerError> {
+        record.validate()?;
+
+        let mut conn = self.connect()?;
+        let tx = conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .change_context(TrackerError)
+            .attach_printable("unable to begin transaction when adding record")?;
+
+        let overlaps = select_records(
+            &tx,
+            "WHERE start_ms < ?2 AND end_ms > ?1 ORDER BY start_ms",
+            params![record.start.0.timestamp_millis(), record.end.0.timestamp_millis()],
+        )?;
+
+        insert_record(&tx, &record).attach_printable("unable to insert record when adding record")?;
+
+        tx.commit()
+            .change_context(TrackerError)
+            .attach_printable("unable to commit transaction when adding record")?;
+
+        Ok(overlaps)
+    }
+
+    fn import<I>(&mut self, records: I) -> Result<usize, TrackerError>
+    where
+        I: IntoIterator<Item = TimeRecord>,
+    {
+        let mut conn = self.connect()?;
+        let tx = conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .change_context(TrackerError)
+            .attach_printable("unable to begin transaction when importing records")?;
+
+        let mut count = 0;
+        for record in records {
+            record.validate()?;
+            insert_record(&tx, &record)
+                .attach_printable_lazy(|| format!("unable to insert record {} when importing records", record.id))?;
+            count += 1;
+        }
+
+        tx.commit()
+            .change_context(TrackerError)
+            .attach_printable("unable to commit transaction when importing records")?;
+
+        Ok(count)
+    }
+
+    fn clear(&mut self) -> Result<(), TrackerError> {
+        self.connect()?
+            .execute("DELETE FROM records", [])
+            .map(|_| ())
+            .change_context(TrackerError)
+            .attach_printable("unable to clear database")
+    }
+
+    fn replace(&mut self, record: TimeRecord) -> Result<TimeRecord, TrackerError> {
+        record.validate()?;
+
+        let mut conn = self.connect()?;
+        let tx = conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .change_context(TrackerError)
+            .attach_printable("unable to begin transaction when replacing record")?;
+
+        let previous = select_record(&tx, record.id)?;
+        let tags = tags_to_sql(&record.task)?;
+
+        tx.execute(
+            "UPDATE records SET start_ms = ?2, end_ms = ?3, project = ?4, description = ?5, tags = ?6,
+            pauses = ?7
+            WHERE id = ?1",
+            params![
+                record.id.to_string(),
+                record.start.0.timestamp_millis(),
+                record.end.0.timestamp_millis(),
+                record.task.project,
+                record.task.description,
+                tags,
+                pauses_to_sql(&record.pauses)?
+            ],
+        )
+        .change_context(TrackerError)
+        .attach_printable("unable to update record")?;
+
+        tx.commit()
+            .change_context(TrackerError)
+            .attach_printable("unable to commit transaction when replacing record")?;
+
+        Ok(previous)
+    }
+
+    fn delete(&mut self, id: RecordId) -> Result<TimeRecord, TrackerError> {
+        let mut conn = self.connect()?;
+        let tx = conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .change_context(TrackerError)
+            .attach_printable("unable to begin transaction when deleting record")?;
+
+        let deleted = select_record(&tx, id)?;
+
+        tx.execute("DELETE FROM records WHERE id = ?1", params![id.to_string()])
+            .change_context(TrackerError)
+            .attach_printable("unable to delete record")?;
+
+        tx.commit()
+            .change_context(TrackerError)
+            .attach_printable("unable to commit transaction when deleting record")?;
+
+        Ok(deleted)
+    }
+}
+
+impl Reporter for SqliteTracker {
+    fn total_duration(
+        &self,
+        timespan: ReportTimespan,
+        config: &ReportConfig,
+    ) -> Result<Duration, ReporterError> {
+        let now = Utc::now();
+        let window = timespan.window(config, now);
+
+        // Pauses lie within their record, so the paused time inside the
+        // window is subtracted from the recorded time inside the window.
+        let total_ms: i64 = self
+            .connect()
+            .change_context(ReporterError)?
+            .query_row(
+                "SELECT
+                    (SELECT COALESCE(SUM(MIN(end_ms, ?2) - MAX(start_ms, ?1)), 0) FROM records
+                    WHERE end_ms > ?1 AND start_ms < ?2)
+                    - (SELECT COALESCE(SUM(MIN(pause_end, ?2) - MAX(pause_start, ?1)), 0) FROM (
+                        SELECT json_extract(pause.value, '$.start_ms') AS pause_start,
+                            json_extract(pause.value, '$.end_ms') AS pause_end
+                        FROM records, json_each(records.pauses) AS pause
+                        WHERE records.end_ms > ?1 AND records.start_ms < ?2
+                    ) WHERE pause_end > ?1 AND pause_start < ?2)",
+                params![window.start.timestamp_millis(), window.end.timestamp_millis()],
+                |row| row.get(0),
+            )
+            .change_context(ReporterError)
+            .attach_printable("failed to query total duration")?;
+
+        let running = running_time(self, &window, config, now)?;
+
+        (TimeDelta::milliseconds(total_ms) + running)
+            .to_std()
+            .change_context(ReporterError)
+            .attach_printable("total duration is negative")
+    }
+}
+
+fn insert_record(conn: &Connection, record: &TimeRecord) -> Result<(), TrackerError> {
+    let tags = tags_to_sql(&record.task)?;
+
+    conn.execute(
+        "INSERT INTO records (id, start_ms, end_ms, project, description, tags, pauses)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            record.id.to_string(),
+            record.start.0.timestamp_millis(),
+            record.end.0.timestamp_millis(),
+            record.task.project,
+            record.task.description,
+            tags,
+            pauses_to_sql(&record.pauses)?
+        ],
+    )
+    .change_context(TrackerError)?;
+
+    Ok(())
+}
+
+/// Inserts the running session. The table must not hold one already.
+fn insert_session(conn: &Connection, session: &Session) -> Result<(), TrackerError> {
+    conn.execute(
+        "INSERT INTO session (id, start_ms, project, description, tags, pauses, paused_at_ms, origin)
+        VALUES (0, ?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            session.start.0.timestamp_millis(),
+            session.task.project,
+            session.task.description,
+            tags_to_sql(&session.task)?,
+            pauses_to_sql(&session.pauses)?,
+            session.paused_at.map(|paused_at| paused_at.timestamp_millis()),
+            origin_to_sql(session.origin.as_ref())?
+        ],
+    )
+    .change_context(TrackerError)?;
+
+    Ok(())
+}
+
+/// Selects the running session, if any.
+fn select_session(conn: &Connection) -> Result<Option<Session>, TrackerError> {
+    conn.query_row(
+        &format!("SELECT {SESSION_COLUMNS} FROM session WHERE id = 0"),
+        [],
+        session_from_row,
+    )
+    .optional()
+    .change_context(TrackerError)
+}
+
+/// Selects the record with the given ID, or fails if there is none.
+fn select_record(conn: &Connection, id: RecordId) -> Result<TimeRecord, TrackerError> {
+    select_records(conn, "WHERE id = ?1", params![id.to_string()])?
+        .pop()
+        .ok_or(TrackerError)
+        .attach_printable(format!("no record with ID {id}"))
+}
+
+/// Selects records, filtered and ordered by the SQL in `clause`.
+fn select_records<P>(conn: &Connection, clause: &str, params: P) -> Result<Vec<TimeRecord>, TrackerError>
+where
+    P: rusqlite::Params,
+{
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT start_ms, end_ms, project, description, tags, id, pauses FROM records {clause}"
+        ))
+        .change_context(TrackerError)
+        .attach_printable("unable to prepare records query")?;
+
+    let records = stmt
+        .query_map(params, |row| {
+            Ok(TimeRecord {
+                id: id_from_row(row, 5)?,
+                start: StartTime(datetime_from_row(row, 0)?),
+                end: EndTime(datetime_from_row(row, 1)?),
+                pauses: pauses_from_row(row, 6)?,
+                task: task_from_row(row, 2)?,
+            })
+        })
+        .change_context(TrackerError)
+        .attach_printable("unable to query records")?
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .change_context(TrackerError)
+        .attach_printable("unable to read records")?;
+
+    Ok(records)
+}
+
+fn migrate(conn: &mut Connection) -> rusqlite::Result<()> {
+    let user_version = |conn: &Connection| conn.pragma_query_value(None, "user_version", |row| row.get::<_, usize>(0));
+    if user_version(conn)? >= MIGRATIONS.len() {
+        return Ok(());
+    }
+
+    // Checked again once the database is locked, another process may have
+    // migrated it in the meantime
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+    let applied = user_version(&tx)?;
+    for (version, migration) in MIGRATIONS.iter().enumerate().skip(applied) {
+        tx.execute_batch(migration)?;
+        tx.pragma_update(None, "user_version", version + 1)?;
+    }
+
+    tx.commit()
+}
+
+fn tags_to_sql(task: &Task) -> Result<String, TrackerError> {
+    serde_json::to_string(&task.tags)
+        .change_context(TrackerError)
+        .attach_printable("unable to serialize tags")
+}
+
+/// Reads the `project`, `description` and `tags` columns starting at `offset`.
+fn task_from_row(row: &Row, offset: usize) -> rusqlite::Result<Task> {
+    let tags: String = row.get(offset + 2)?;
+
+    Ok(Task {
+        project: row.get(offset)?,
+        description: row.get(offset + 1)?,
+        tags: serde_json::from_str(&tags)
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(offset + 2, Type::Text, Box::new(e)))?,
+    })
+}
+
+/// Reads the columns in `SESSION_COLUMNS`.
+fn session_from_row(row: &Row) -> rusqlite::Result<Session> {
+    let paused_at: Option<i64> = row.get(5)?;
+    let origin: Option<String> = row.get(6)?;
+
+    Ok(Session {
+        start: StartTime(datetime_from_row(row, 0)?),
+        task: task_from_row(row, 1)?,
+        pauses: pauses_from_row(row, 4)?,
+        paused_at: paused_at.map(|ms| from_millis(ms, 5)).transpose()?,
+        origin: origin
+            .map(|origin| serde_json::from_str(&origin))
+            .transpose()
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(6, Type::Text, Box::new(e)))?,
+    })
+}
+
+fn origin_to_sql(origin: Option<&Origin>) -> Result<Option<String>, TrackerError> {
+    origin
+        .map(serde_json::to_string)
+        .transpose()
+        .change_context(TrackerError)
+        .attach_printable("unable to serialize session origin")
+}
+
+fn pauses_to_sql(pauses: &[Pause]) -> Result<String, TrackerError> {
+    let rows = pauses
+        .iter()
+        .map(|pause| PauseRow {
+            start_ms: pause.start.timestamp_millis(),
+            end_ms: pause.end.timestamp_millis(),
+        })
+        .collect::<Vec<_>>();
+
+    serde_json::to_string(&rows)
+        .change_context(TrackerError)
+        .attach_printable("unable to serialize pauses")
+}
+
+fn pauses_from_row(row: &Row, index: usize) -> rusqlite::Result<Vec<Pause>> {
+    let pauses: String = row.get(index)?;
+    let rows: Vec<PauseRow> = serde_json::from_str(&pauses)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(index, Type::Text, Box::new(e)))?;
+
+    rows.into_iter()
+        .map(|pause| {
+            Ok(Pause {
+                start: from_millis(pause.start_ms, index)?,
+                end: from_millis(pause.end_ms, index)?,
+            })
+        })
+        .collect()
+}
+
+fn id_from_row(row: &Row, index: usize) -> rusqlite::Result<RecordId> {
+    let id: String = row.get(index)?;
+
+    Uuid::parse_str(&id)
+        .map(RecordId::from)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(index, Type::Text, Box::new(e)))
+}
+
+fn datetime_from_row(row: &Row, index: usize) -> rusqlite::Result<DateTime<Utc>> {
+    from_millis(row.get(index)?, index)
+}
+
+/// Drops the part of `at` that is not stored, so that records and sessions
+/// returned by a change equal the ones read back.
+fn stored_precision(at: DateTime<Utc>) -> DateTime<Utc> {
+    at.trunc_subsecs(3)
+}
+
+/// Converts a timestamp read from column `index`, failing if it is out of
+/// range.
+fn from_millis(ms: i64, index: usize) -> rusqlite::Result<DateTime<Utc>> {
+    DateTime::from_timestamp_millis(ms).ok_or(rusqlite::Error::IntegralValueOutOfRange(index, ms))
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_fs::{prelude::PathChild, TempDir};
+
+    use super::*;
+    use crate::feature::tracker::tlib::tracker_tests;
+
+    fn new_tracker(temp: &TempDir) -> SqliteTracker {
+        SqliteTracker::new(temp.child("db.sqlite").to_path_buf())
+    }
+
+    tracker_tests!(new_tracker);
+
+    #[test]
+    fn total_duration_matches_records() {
+        let temp = TempDir::new().unwrap();
+        let mut tracker = new_tracker(&temp);
+
+        tracker.start(Task::default()).unwrap();
+        std::thread::sleep(Duration::from_millis(10));
+        tracker.stop().unwrap();
+
+        let expected = tracker
+            .records()
+            .unwrap()
+            .map(|rec| (rec.end.0 - rec.start.0).num_milliseconds())
+            .sum::<i64>();
+
+        let duration = tracker
+            .total_duration(ReportTimespan::Last(Duration::from_secs(60)), &ReportConfig::default())
+            .unwrap();
+
+        assert_eq!(duration, Duration::from_millis(expected as u64));
+    }
+
+    #[test]
+    fn concurrent_changes_wait_for_each_other() {
+        let temp = TempDir::new().unwrap();
+        let temp = &temp;
+        let start = DateTime::parse_from_rfc3339("2026-10-15T09:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        // Deferred transactions that read and then write fail with
+        // SQLITE_BUSY instead of waiting for each other
+        std::thread::scope(|scope| {
+            for thread in 0..4 {
+                scope.spawn(move || {
+                    let mut tracker = new_tracker(temp);
+                    for i in 0..10 {
+                        let start = start + TimeDelta::hours(thread * 10 + i);
+                        tracker
+                            .add(TimeRecord {
+                                id: RecordId::new(),
+                                start: start.into(),
+                                end: (start + TimeDelta::minutes(30)).into(),
+                                pauses: Vec::new(),
+                                task: Task::default(),
+                            })
+                            .unwrap();
+                    }
+                });
+            }
+        });
+
+        assert_eq!(new_tracker(temp).records().unwrap().count(), 40);
+    }
+
+    #[test]
+    fn returned_record_equals_stored_record() {
+        let temp = TempDir::new().unwrap();
+        let mut tracker = new_tracker(&temp);
+
+        let at = |s: &str| DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc);
+        tracker
+            .start_at(Task::default(), at("2026-10-15T09:00:00.123456789Z").into())
+            .unwrap();
+        let paused = tracker.pause_at(at("2026-10-15T09:30:00.123456789Z")).unwrap();
+        assert_eq!(Some(paused), tracker.session().unwrap());
+        tracker.resume_at(at("2026-10-15T09:45:00.123456789Z")).unwrap();
+
+        let switched = tracker
+            .switch_at(Task::default(), at("2026-10-15T10:00:00.123456789Z").into())
+            .unwrap();
+        let stopped = tracker.stop_at(at("2026-10-15T11:00:00.123456789Z").into()).unwrap();
+
+        let stored = tracker.records().unwrap().collect::<Vec<_>>();
+        assert_eq!(stored, vec![switched.unwrap(), stopped]);
+    }
+
+    #[test]
+    fn total_duration_clips_records_to_window() {
+        let temp = TempDir::new().unwrap();
+        let tracker = new_tracker(&temp);
+
+        let ms = |s: &str| DateTime::parse_from_rfc3339(s).unwrap().timestamp_millis();
+        let conn = tracker.connect().unwrap();
+        for (start, end) in [
+            ("2026-10-14T23:00:00Z", "2026-10-15T01:00:00Z"),
+            ("2026-10-15T10:00:00Z", "2026-10-15T11:00:00Z"),
+            ("2026-10-15T23:30:00Z", "2026-10-16T02:00:00Z"),
+            ("2026-10-16T03:00:00Z", "2026-10-16T04:00:00Z"),
+        ] {
+            conn.execute(
+                "INSERT INTO records (id, start_ms, end_ms) VALUES (?1, ?2, ?3)",
+                params![RecordId::new().to_string(), ms(start), ms(end)],
+            )
+            .unwrap();
+        }
+
+        let day = chrono::NaiveDate::from_ymd_opt(2026, 10, 15).unwrap();
+        let duration = tracker
+            .total_duration(ReportTimespan::Between(day, day), &ReportConfig::default())
+            .unwrap();
 
-//     fn is_running(&self) -> bool {
-//         todo!()
-//     }
+        assert_eq!(duration, Duration::from_secs(150 * 60));
+    }
 
-//     fn stop(&mut self) -> error_stack::Result<(), super::TrackerError> {
-//         todo!("lockfile")
-//     }
+    #[test]
+    fn total_duration_leaves_out_pauses() {
+        let temp = TempDir::new().unwrap();
+        let mut tracker = new_tracker(&temp);
 
-//     fn has_records(&self) -> bool {
-//         todo!()
-//     }
+        let utc = |s: &str| DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc);
+        tracker
+            .add(TimeRecord {
+                id: RecordId::new(),
+                start: utc("2026-10-15T09:00:00Z").into(),
+                end: utc("2026-10-16T01:00:00Z").into(),
+                pauses: vec![
+                    Pause {
+                        start: utc("2026-10-15T10:00:00Z"),
+                        end: utc("2026-10-15T10:30:00Z"),
+                    },
+                    // Only the part inside the window is left out
+                    Pause {
+                        start: utc("2026-10-15T23:00:00Z"),
+                        end: utc("2026-10-16T00:30:00Z"),
+                    },
+                ],
+                task: Task::default(),
+            })
+            .unwrap();
 
-//     fn records(&self) -> error_stack::Result<impl Iterator<Item = super::TimeRecord>, super::TrackerError> {
-//         todo!("database");
-//         Ok(())
-//     }
-// }
+        let day = chrono::NaiveDate::from_ymd_opt(2026, 10, 15).unwrap();
+        let duration = tracker
+            .total_duration(ReportTimespan::Between(day, day), &ReportConfig::default())
+            .unwrap();
 
-// impl Reporter for SqliteTracker {
-//     fn total_duration(&self, timespan: super::ReportTimespan) -> error_stack::Result<std::time::Duration, super::ReporterError> {
-//         // query the database
+        assert_eq!(duration, Duration::from_secs(13 * 3600 + 30 * 60));
+    }
 
-//     }
-// }
\ No newline at end of file
+    #[test]
+    fn total_duration_includes_running_session() {
+        let temp = TempDir::new().unwrap();
+        let mut tracker = new_tracker(&temp);
+
+        tracker.start(Task::default()).unwrap();
+        std::thread::sleep(Duration::from_millis(10));
+
+        let config = ReportConfig {
+            include_running: true,
+            ..Default::default()
+        };
+        let duration = tracker
+            .total_duration(ReportTimespan::Last(Duration::from_secs(60)), &config)
+            .unwrap();
+
+        assert!(duration >= Duration::from_millis(10));
+    }
+
+    #[test]
+    fn reads_databases_created_before_tasks() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.child("db.sqlite");
+
+        // Given a database with only the first migration applied
+        let conn = Connection::open(&path).unwrap();
+        conn.execute_batch(MIGRATIONS[0]).unwrap();
+        conn.pragma_update(None, "user_version", 1).unwrap();
+        conn.execute("INSERT INTO records (start_ms, end_ms) VALUES (0, 1000)", [])
+            .unwrap();
+        drop(conn);
+
+        // Then the record is read with an empty task
+        let tracker = SqliteTracker::new(path.to_path_buf());
+        let record = tracker.records().unwrap().next().unwrap();
+        assert_eq!(record.task, Task::default());
+    }
+
+    #[test]
+    fn records_created_before_ids_get_distinct_ids() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.child("db.sqlite");
+
+        // Given a database with records written before IDs existed
+        let conn = Connection::open(&path).unwrap();
+        conn.execute_batch(MIGRATIONS[0]).unwrap();
+        conn.execute_batch(MIGRATIONS[1]).unwrap();
+        conn.pragma_update(None, "user_version", 2).unwrap();
+        conn.execute("INSERT INTO records (start_ms, end_ms) VALUES (0, 1000), (2000, 3000)", [])
+            .unwrap();
+        drop(conn);
+
+        // Then every record gets its own valid ID
+        let tracker = SqliteTracker::new(path.to_path_buf());
+        let ids = tracker.records().unwrap().map(|record| record.id).collect::<Vec<_>>();
+        assert_eq!(ids.len(), 2);
+        assert_ne!(ids[0], ids[1]);
+        assert!(ids.iter().all(|id| !id.is_nil()));
+    }
+
+    #[test]
+    fn records_inserted_without_ids_get_ids() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.child("db.sqlite");
+
+        // Given a database where a record was inserted without an ID after
+        // the ID column was added
+        let conn = Connection::open(&path).unwrap();
+        for migration in &MIGRATIONS[..5] {
+            conn.execute_batch(migration).unwrap();
+        }
+        conn.pragma_update(None, "user_version", 5).unwrap();
+        conn.execute("INSERT INTO records (start_ms, end_ms) VALUES (0, 1000)", [])
+            .unwrap();
+        drop(conn);
+
+        // Then it gets an ID, and records without one are refused
+        let tracker = SqliteTracker::new(path.to_path_buf());
+        let record = tracker.records().unwrap().next().unwrap();
+        assert!(!record.id.is_nil());
+
+        let conn = tracker.connect().unwrap();
+        assert!(conn
+            .execute("INSERT INTO records (start_ms, end_ms) VALUES (2000, 3000)", [])
+            .is_err());
+    }
+
+    #[test]
+    fn corrupt_columns_are_an_error() {
+        let temp = TempDir::new().unwrap();
+        let mut tracker = new_tracker(&temp);
+        tracker.start(Task::default()).unwrap();
+        tracker.stop().unwrap();
+
+        // Tags that are not a JSON list are not read as no tags
+        let conn = tracker.connect().unwrap();
+        conn.execute("UPDATE records SET tags = '[\"web'", []).unwrap();
+        assert!(tracker.records().is_err());
+
+        // Timestamps out of range are not read as 1970
+        conn.execute("UPDATE records SET tags = '[]', start_ms = ?1", [i64::MAX])
+            .unwrap();
+        assert!(tracker.records().is_err());
+    }
+
+    #[test]
+    fn session_is_kept_in_database() {
+        let temp = TempDir::new().unwrap();
+
+        let mut tracker = new_tracker(&temp);
+        tracker.start(Task::default()).unwrap();
+
+        // Then a second tracker on the same database sees the running session
+        let tracker = new_tracker(&temp);
+        assert!(tracker.is_running());
+    }
+}
@@ -29,10 +29,15 @@ pub fn tracing() {
     use tracing_subscriber::util::SubscriberInitExt;
     use tracing_subscriber::layer::SubscriberExt;
     use tracing_error::ErrorLayer;
+    use tracing_subscriber::filter::LevelFilter;
 
     tracing_subscriber::registry()
-        .with(tracing_subscriber::fmt::layer().pretty())
-        .with(EnvFilter::builder().from_env_lossy())
+        .with(tracing_subscriber::fmt::layer().pretty().with_writer(std::io::stderr))
+        .with(
+            EnvFilter::builder()
+                .with_default_directive(LevelFilter::WARN.into())
+                .from_env_lossy(),
+        )
         .with(ErrorLayer::default())
         .init();
 }
@@ -1,5 +1,6 @@
 use assert_cmd::Command;
 use assert_fs::{fixture::ChildPath, prelude::PathChild, TempDir};
+use predicates::prelude::PredicateBooleanExt;
 use testresult::TestResult;
 
 fn tracking_paths() -> (TempDir, ChildPath, ChildPath) {
@@ -45,7 +46,7 @@ fn status_code_is_error_if_no_command_specified() -> TestResult {
 fn start_command_starts_tracking() -> TestResult {
     // track --db-dir PATH --lockfile PATH start
 
-    let (temp, db, lockfile) = tracking_paths();
+    let (_temp, db, lockfile) = tracking_paths();
 
     start_tracking(&db, &lockfile)?;
 
@@ -58,7 +59,7 @@ fn start_command_starts_tracking() -> TestResult {
 fn stop_command_stops_tracking() -> TestResult {
     // track --db-dir PATH --lockfile PATH stop
 
-    let (temp, db, lockfile) = tracking_paths();
+    let (_temp, db, lockfile) = tracking_paths();
 
     start_tracking(&db, &lockfile)?;
 
@@ -73,7 +74,7 @@ fn stop_command_stops_tracking() -> TestResult {
 
 #[test]
 fn report_command_generates_report() -> TestResult {
-    let (temp, db, lockfile) = tracking_paths();
+    let (_temp, db, lockfile) = tracking_paths();
 
     start_tracking(&db, &lockfile)?;
     stop_tracking(&db, &lockfile)?;
@@ -89,3 +90,937 @@ fn report_command_generates_report() -> TestResult {
 
     Ok(())
 }
+
+#[test]
+fn start_command_records_project_tags_and_description() -> TestResult {
+    let (_temp, db, lockfile) = tracking_paths();
+
+    Command::cargo_bin("track")?
+        .arg("--db-dir")
+        .arg(db.to_path_buf())
+        .arg("--lockfile")
+        .arg(lockfile.to_path_buf())
+        .args(["start", "--project", "website", "--tag", "css", "--tag", "bug", "fix header"])
+        .assert()
+        .success();
+
+    stop_tracking(&db, &lockfile)?;
+
+    let records: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&db)?)?;
+    let record = &records["records"][0];
+
+    assert_eq!(record["project"], "website");
+    assert_eq!(record["description"], "fix header");
+    assert_eq!(record["tags"], serde_json::json!(["bug", "css"]));
+
+    Ok(())
+}
+
+#[test]
+fn report_command_accepts_calendar_timespans() -> TestResult {
+    let (_temp, db, lockfile) = tracking_paths();
+
+    start_tracking(&db, &lockfile)?;
+    stop_tracking(&db, &lockfile)?;
+
+    for timespan in [
+        vec!["--today"],
+        vec!["--yesterday"],
+        vec!["--week"],
+        vec!["--last-week"],
+        vec!["--month"],
+        vec!["--from", "2026-10-01"],
+        vec!["--from", "2026-10-01", "--to", "2026-10-15"],
+        vec!["--today", "--tz", "America/New_York"],
+    ] {
+        Command::cargo_bin("track")?
+            .arg("--db-dir")
+            .arg(db.to_path_buf())
+            .arg("--lockfile")
+            .arg(lockfile.to_path_buf())
+            .arg("report")
+            .args(&timespan)
+            .assert()
+            .success()
+            .stdout("00:00:00\n");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn report_command_rejects_invalid_timespans() -> TestResult {
+    let (_temp, db, lockfile) = tracking_paths();
+
+    Command::cargo_bin("track")?
+        .arg("--db-dir")
+        .arg(db.to_path_buf())
+        .arg("--lockfile")
+        .arg(lockfile.to_path_buf())
+        .args(["report", "--from", "2026-10-15", "--to", "2026-10-01"])
+        .assert()
+        .failure();
+
+    Command::cargo_bin("track")?
+        .arg("--db-dir")
+        .arg(db.to_path_buf())
+        .arg("--lockfile")
+        .arg(lockfile.to_path_buf())
+        .args(["report", "--today", "--week"])
+        .assert()
+        .failure();
+
+    Command::cargo_bin("track")?
+        .arg("--db-dir")
+        .arg(db.to_path_buf())
+        .arg("--lockfile")
+        .arg(lockfile.to_path_buf())
+        .args(["report", "--today", "--tz", "Mars/Olympus_Mons"])
+        .assert()
+        .failure();
+
+    Ok(())
+}
+
+#[test]
+fn report_command_marks_running_session() -> TestResult {
+    let (_temp, db, lockfile) = tracking_paths();
+
+    start_tracking(&db, &lockfile)?;
+
+    Command::cargo_bin("track")?
+        .arg("--db-dir")
+        .arg(db.to_path_buf())
+        .arg("--lockfile")
+        .arg(lockfile.to_path_buf())
+        .arg("report")
+        .assert()
+        .success()
+        .stdout("00:00:00 (running)\n");
+
+    Command::cargo_bin("track")?
+        .arg("--db-dir")
+        .arg(db.to_path_buf())
+        .arg("--lockfile")
+        .arg(lockfile.to_path_buf())
+        .args(["report", "--exclude-running"])
+        .assert()
+        .success()
+        .stdout("00:00:00\n");
+
+    Ok(())
+}
+
+#[test]
+fn report_command_groups_by_project_and_day() -> TestResult {
+    let (_temp, db, lockfile) = tracking_paths();
+
+    Command::cargo_bin("track")?
+        .arg("--db-dir")
+        .arg(db.to_path_buf())
+        .arg("--lockfile")
+        .arg(lockfile.to_path_buf())
+        .args(["start", "--project", "website"])
+        .assert()
+        .success();
+    stop_tracking(&db, &lockfile)?;
+
+    let output = Command::cargo_bin("track")?
+        .arg("--db-dir")
+        .arg(db.to_path_buf())
+        .arg("--lockfile")
+        .arg(lockfile.to_path_buf())
+        .args(["report", "--group-by", "project,day"])
+        .output()?;
+
+    let stdout = String::from_utf8(output.stdout)?;
+    let lines = stdout.lines().collect::<Vec<_>>();
+
+    assert!(output.status.success());
+    assert!(lines[0].starts_with("PROJECT  DAY"));
+    assert!(lines[1].starts_with("website"));
+    assert!(lines.last().unwrap().starts_with("TOTAL"));
+
+    Ok(())
+}
+
+#[test]
+fn report_command_rejects_unknown_grouping() -> TestResult {
+    let (_temp, db, lockfile) = tracking_paths();
+
+    Command::cargo_bin("track")?
+        .arg("--db-dir")
+        .arg(db.to_path_buf())
+        .arg("--lockfile")
+        .arg(lockfile.to_path_buf())
+        .args(["report", "--group-by", "year"])
+        .assert()
+        .failure();
+
+    Ok(())
+}
+
+#[test]
+fn report_command_outputs_json() -> TestResult {
+    let (_temp, db, lockfile) = tracking_paths();
+
+    start_tracking(&db, &lockfile)?;
+    stop_tracking(&db, &lockfile)?;
+
+    let output = Command::cargo_bin("track")?
+        .arg("--db-dir")
+        .arg(db.to_path_buf())
+        .arg("--lockfile")
+        .arg(lockfile.to_path_buf())
+        .args(["report", "--format", "json"])
+        .output()?;
+
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+
+    assert!(output.status.success());
+    assert_eq!(report["schema"], 1);
+    assert_eq!(report["running"], false);
+    assert!(report["records"].is_array());
+
+    Ok(())
+}
+
+#[test]
+fn report_command_outputs_csv() -> TestResult {
+    let (_temp, db, lockfile) = tracking_paths();
+
+    Command::cargo_bin("track")?
+        .arg("--db-dir")
+        .arg(db.to_path_buf())
+        .arg("--lockfile")
+        .arg(lockfile.to_path_buf())
+        .args(["report", "--format", "csv", "--group-by", "project"])
+        .assert()
+        .success()
+        .stdout("project,duration_seconds\n");
+
+    Command::cargo_bin("track")?
+        .arg("--db-dir")
+        .arg(db.to_path_buf())
+        .arg("--lockfile")
+        .arg(lockfile.to_path_buf())
+        .args(["report", "--format", "csv"])
+        .assert()
+        .success()
+        .stdout("start,end,duration_seconds,project,description,tags,id\n");
+
+    Ok(())
+}
+
+#[test]
+fn status_command_exit_code_reflects_state() -> TestResult {
+    let (_temp, db, lockfile) = tracking_paths();
+
+    Command::cargo_bin("track")?
+        .arg("--db-dir")
+        .arg(db.to_path_buf())
+        .arg("--lockfile")
+        .arg(lockfile.to_path_buf())
+        .arg("status")
+        .assert()
+        .code(2)
+        .stdout("Stopped\n");
+
+    Command::cargo_bin("track")?
+        .arg("--db-dir")
+        .arg(db.to_path_buf())
+        .arg("--lockfile")
+        .arg(lockfile.to_path_buf())
+        .args(["start", "--project", "website"])
+        .assert()
+        .success();
+
+    let output = Command::cargo_bin("track")?
+        .arg("--db-dir")
+        .arg(db.to_path_buf())
+        .arg("--lockfile")
+        .arg(lockfile.to_path_buf())
+        .args(["status", "--format", "json"])
+        .output()?;
+
+    let status: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(status["running"], true);
+    assert_eq!(status["project"], "website");
+
+    track(&db, &lockfile, &["pause"]).success();
+    track(&db, &lockfile, &["status"]).code(3);
+
+    track(&db, &lockfile, &["resume"]).success();
+    track(&db, &lockfile, &["status"]).code(0);
+
+    Ok(())
+}
+
+fn add_record(db: &ChildPath, lockfile: &ChildPath, from: &str, to: &str) -> assert_cmd::assert::Assert {
+    Command::cargo_bin("track")
+        .unwrap()
+        .arg("--db-dir")
+        .arg(db.to_path_buf())
+        .arg("--lockfile")
+        .arg(lockfile.to_path_buf())
+        .args(["--tz", "UTC", "add", "--date", "2026-10-15", "--from", from, "--to", to])
+        .assert()
+}
+
+#[test]
+fn add_command_inserts_record() -> TestResult {
+    let (_temp, db, lockfile) = tracking_paths();
+
+    add_record(&db, &lockfile, "09:00", "10:30")
+        .success()
+        .stdout(predicates::str::is_match("^Record [0-9a-f]{8} added\n$")?);
+
+    Command::cargo_bin("track")?
+        .arg("--db-dir")
+        .arg(db.to_path_buf())
+        .arg("--lockfile")
+        .arg(lockfile.to_path_buf())
+        .args(["--tz", "UTC", "report", "--from", "2026-10-15", "--to", "2026-10-15"])
+        .assert()
+        .success()
+        .stdout("01:30:00\n");
+
+    Ok(())
+}
+
+#[test]
+fn add_command_rejects_inverted_range_and_warns_about_overlaps() -> TestResult {
+    let (_temp, db, lockfile) = tracking_paths();
+
+    add_record(&db, &lockfile, "10:30", "09:00").failure();
+
+    add_record(&db, &lockfile, "09:00", "10:30").success();
+    add_record(&db, &lockfile, "10:00", "11:00")
+        .success()
+        .stderr(predicates::str::contains("warning: overlaps"));
+
+    Ok(())
+}
+
+fn track(db: &ChildPath, lockfile: &ChildPath, args: &[&str]) -> assert_cmd::assert::Assert {
+    Command::cargo_bin("track")
+        .unwrap()
+        .arg("--db-dir")
+        .arg(db.to_path_buf())
+        .arg("--lockfile")
+        .arg(lockfile.to_path_buf())
+        .args(["--tz", "UTC"])
+        .args(args)
+        .assert()
+}
+
+#[test]
+fn backdated_start_and_stop_are_recorded() -> TestResult {
+    let (_temp, db, lockfile) = tracking_paths();
+
+    track(&db, &lockfile, &["start", "--at", "2026-10-15 09:00"]).success();
+    track(&db, &lockfile, &["stop", "--at", "2026-10-15 10:30"]).success();
+
+    track(&db, &lockfile, &["report", "--from", "2026-10-15", "--to", "2026-10-15"])
+        .success()
+        .stdout("01:30:00\n");
+
+    Ok(())
+}
+
+#[test]
+fn start_ago_is_reflected_in_status() -> TestResult {
+    let (_temp, db, lockfile) = tracking_paths();
+
+    track(&db, &lockfile, &["start", "--ago", "15m"]).success();
+
+    track(&db, &lockfile, &["status"])
+        .success()
+        .stdout(predicates::str::starts_with("Running for 00:15:0"));
+
+    Ok(())
+}
+
+#[test]
+fn invalid_backdated_times_are_rejected() -> TestResult {
+    let (_temp, db, lockfile) = tracking_paths();
+
+    // Both --at and --ago, an unparsable time and a time in the future
+    track(&db, &lockfile, &["start", "--at", "09:00", "--ago", "15m"]).failure();
+    track(&db, &lockfile, &["start", "--ago", "soon"]).failure();
+    track(&db, &lockfile, &["start", "--at", "2999-01-01 09:00"]).failure();
+    assert!(!lockfile.exists());
+
+    // Stopping before the session started keeps it running
+    track(&db, &lockfile, &["start", "--ago", "15m"]).success();
+    track(&db, &lockfile, &["stop", "--ago", "1h"]).failure();
+    assert!(lockfile.exists());
+
+    Ok(())
+}
+
+/// Adds a record on 2026-10-15 (UTC) and returns its short ID.
+fn added_id(db: &ChildPath, lockfile: &ChildPath, from: &str, to: &str) -> String {
+    let output = add_record(db, lockfile, from, to).success().get_output().stdout.clone();
+    String::from_utf8(output).unwrap().split_whitespace().nth(1).unwrap().to_string()
+}
+
+#[test]
+fn edit_command_changes_record() -> TestResult {
+    let (_temp, db, lockfile) = tracking_paths();
+    let id = added_id(&db, &lockfile, "09:00", "10:00");
+
+    track(&db, &lockfile, &["edit", &id, "--end", "10:30", "-p", "website", "-t", "css"])
+        .success()
+        .stdout(format!("Record {id} updated\n"));
+
+    track(&db, &lockfile, &["--format", "csv", "report", "--from", "2026-10-15"])
+        .success()
+        .stdout(predicates::str::contains(format!(
+            "2026-10-15T09:00:00Z,2026-10-15T10:30:00Z,5400,website,,css,{id}"
+        )));
+
+    Ok(())
+}
+
+#[test]
+fn edit_command_rejects_invalid_changes() -> TestResult {
+    let (_temp, db, lockfile) = tracking_paths();
+    let id = added_id(&db, &lockfile, "09:00", "10:00");
+
+    // Nothing to change, an end before the start and an unknown ID
+    track(&db, &lockfile, &["edit", &id]).failure();
+    track(&db, &lockfile, &["edit", &id, "--end", "08:00"]).failure();
+    track(&db, &lockfile, &["edit", "ffffffffff", "--end", "11:00"]).failure();
+
+    track(&db, &lockfile, &["report", "--from", "2026-10-15", "--to", "2026-10-15"])
+        .success()
+        .stdout("01:00:00\n");
+
+    Ok(())
+}
+
+#[test]
+fn delete_command_removes_record_and_journals_it() -> TestResult {
+    let (_temp, db, lockfile) = tracking_paths();
+    let id = added_id(&db, &lockfile, "09:00", "10:00");
+    added_id(&db, &lockfile, "11:00", "11:30");
+
+    track(&db, &lockfile, &["delete", &id])
+        .success()
+        .stdout(format!("Record {id} deleted\n"));
+    track(&db, &lockfile, &["delete", &id]).failure();
+
+    track(&db, &lockfile, &["report", "--from", "2026-10-15", "--to", "2026-10-15"])
+        .success()
+        .stdout("00:30:00\n");
+
+    let journal = std::fs::read_to_string(format!("{}.journal", db.display()))?;
+    assert!(journal.contains(r#""change":"delete""#));
+
+    Ok(())
+}
+
+#[test]
+fn undo_reverts_a_stray_stop() -> TestResult {
+    let (_temp, db, lockfile) = tracking_paths();
+
+    track(&db, &lockfile, &["start", "--at", "2026-10-15 09:00"]).success();
+    track(&db, &lockfile, &["stop", "--at", "2026-10-15 10:00"]).success();
+
+    track(&db, &lockfile, &["undo"])
+        .success()
+        .stdout(predicates::str::starts_with("Undid stop of record "));
+    assert!(lockfile.exists());
+
+    track(&db, &lockfile, &["redo"])
+        .success()
+        .stdout(predicates::str::starts_with("Redid stop of record "));
+    assert!(!lockfile.exists());
+    track(&db, &lockfile, &["report", "--from", "2026-10-15", "--to", "2026-10-15"])
+        .success()
+        .stdout("01:00:00\n");
+
+    Ok(())
+}
+
+#[test]
+fn undo_restores_a_deleted_record() -> TestResult {
+    let (_temp, db, lockfile) = tracking_paths();
+    let id = added_id(&db, &lockfile, "09:00", "10:00");
+
+    track(&db, &lockfile, &["delete", &id]).success();
+    track(&db, &lockfile, &["undo"])
+        .success()
+        .stdout(format!("Undid delete of record {id}\n"));
+
+    track(&db, &lockfile, &["report", "--from", "2026-10-15", "--to", "2026-10-15"])
+        .success()
+        .stdout("01:00:00\n");
+
+    // The add is next, then there is nothing left to undo
+    track(&db, &lockfile, &["undo"]).success().stdout(format!("Undid add of record {id}\n"));
+    track(&db, &lockfile, &["undo"]).success().stdout("Nothing to undo\n");
+
+    Ok(())
+}
+
+#[test]
+fn history_command_lists_changes() -> TestResult {
+    let (_temp, db, lockfile) = tracking_paths();
+
+    track(&db, &lockfile, &["history"]).success().stdout("No changes\n");
+
+    let id = added_id(&db, &lockfile, "09:00", "10:00");
+    track(&db, &lockfile, &["delete", &id]).success();
+    track(&db, &lockfile, &["undo"]).success();
+
+    let output = track(&db, &lockfile, &["history"]).success().get_output().stdout.clone();
+    let lines = String::from_utf8(output)?.lines().map(String::from).collect::<Vec<_>>();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("WHEN"));
+    assert!(lines[1].contains("delete (undone)"));
+    assert!(lines[2].contains("add") && lines[2].contains(&id));
+
+    Ok(())
+}
+
+#[test]
+fn history_command_prints_json_and_csv() -> TestResult {
+    let (_temp, db, lockfile) = tracking_paths();
+
+    track(&db, &lockfile, &["--format", "json", "history"])
+        .success()
+        .stdout(predicates::str::contains("\"changes\": []"));
+
+    let id = added_id(&db, &lockfile, "09:00", "10:00");
+    track(&db, &lockfile, &["delete", &id]).success();
+    track(&db, &lockfile, &["undo"]).success();
+
+    let output = track(&db, &lockfile, &["--format", "json", "history"])
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let history: serde_json::Value = serde_json::from_slice(&output)?;
+    assert_eq!(history["schema"], 1);
+    let changes = history["changes"].as_array().unwrap();
+    assert_eq!(changes.len(), 2);
+    assert_eq!(changes[0]["change"], "delete");
+    assert_eq!(changes[0]["undone"], true);
+    assert_eq!(changes[1]["change"], "add");
+    assert!(changes[1]["record"].as_str().unwrap().starts_with(&id));
+
+    let output = track(&db, &lockfile, &["--format", "csv", "history"])
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let lines = String::from_utf8(output)?.lines().map(String::from).collect::<Vec<_>>();
+    assert_eq!(lines[0], "at,change,undone,record,start,end");
+    assert!(lines[1].contains(",delete,true,"));
+
+    Ok(())
+}
+
+#[test]
+fn cancel_discards_the_running_session() -> TestResult {
+    let (_temp, db, lockfile) = tracking_paths();
+
+    track(&db, &lockfile, &["cancel"]).failure();
+
+    // A short session is cancelled without asking
+    track(&db, &lockfile, &["start"]).success();
+    track(&db, &lockfile, &["cancel"]).success().stdout("Tracker cancelled\n");
+    assert!(!lockfile.exists());
+    assert!(!db.exists());
+
+    Ok(())
+}
+
+#[test]
+fn cancel_asks_before_discarding_a_long_session() -> TestResult {
+    let (_temp, db, lockfile) = tracking_paths();
+    track(&db, &lockfile, &["start", "--ago", "1h"]).success();
+
+    let cancel = |stdin: &str, args: &[&str]| {
+        Command::cargo_bin("track")
+            .unwrap()
+            .arg("--db-dir")
+            .arg(db.to_path_buf())
+            .arg("--lockfile")
+            .arg(lockfile.to_path_buf())
+            .arg("cancel")
+            .args(args)
+            .write_stdin(stdin)
+            .assert()
+    };
+
+    cancel("n\n", &[])
+        .failure()
+        .stderr(predicates::str::contains("Discard the session that has been running for 01:00:"));
+    assert!(lockfile.exists());
+
+    cancel("y\n", &[]).success();
+    assert!(!lockfile.exists());
+
+    track(&db, &lockfile, &["start", "--ago", "1h"]).success();
+    cancel("", &["--yes"]).success();
+    assert!(!lockfile.exists());
+
+    Ok(())
+}
+
+#[test]
+fn paused_time_is_left_out_of_the_report() -> TestResult {
+    let (_temp, db, lockfile) = tracking_paths();
+
+    track(&db, &lockfile, &["pause"]).failure();
+
+    track(&db, &lockfile, &["start", "--at", "2026-10-15 09:00"]).success();
+    track(&db, &lockfile, &["pause", "--at", "2026-10-15 10:00"])
+        .success()
+        .stdout("Tracker paused\n");
+    track(&db, &lockfile, &["status"])
+        .code(3)
+        .stdout(predicates::str::contains("paused since 2026-10-15 10:00:00 UTC"));
+    track(&db, &lockfile, &["pause"]).failure();
+
+    track(&db, &lockfile, &["resume", "--at", "2026-10-15 10:30"])
+        .success()
+        .stdout("Tracker resumed\n");
+    track(&db, &lockfile, &["resume"]).failure();
+    track(&db, &lockfile, &["stop", "--at", "2026-10-15 12:00"]).success();
+
+    track(&db, &lockfile, &["report", "--from", "2026-10-15", "--to", "2026-10-15"])
+        .success()
+        .stdout("02:30:00\n");
+
+    Ok(())
+}
+
+#[test]
+fn switch_leaves_no_gap_between_records() -> TestResult {
+    let (_temp, db, lockfile) = tracking_paths();
+
+    // Nothing is running, so switch starts the tracker
+    track(&db, &lockfile, &["switch", "--at", "2026-10-15 09:00", "--project", "website"])
+        .success()
+        .stdout("Tracker started\n");
+    track(&db, &lockfile, &["switch", "--at", "2026-10-15 10:30", "--project", "blog", "draft"])
+        .success()
+        .stdout(predicates::str::is_match("^Record [0-9a-f]{8} added, tracker started\n$")?);
+    track(&db, &lockfile, &["stop", "--at", "2026-10-15 11:00"]).success();
+
+    let records: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&db)?)?;
+    let records = records["records"].as_array().unwrap();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0]["project"], "website");
+    assert_eq!(records[0]["end"], "2026-10-15T10:30:00Z");
+    assert_eq!(records[1]["start"], records[0]["end"]);
+    assert_eq!(records[1]["project"], "blog");
+    assert_eq!(records[1]["description"], "draft");
+
+    Ok(())
+}
+
+#[test]
+fn log_lists_filtered_and_sorted_records() -> TestResult {
+    let (_temp, db, lockfile) = tracking_paths();
+    let log = |args: &[&str]| {
+        let mut all = vec!["log", "--from", "2026-10-15", "--to", "2026-10-15"];
+        all.extend(args);
+        track(&db, &lockfile, &all)
+    };
+
+    log(&[]).success().stdout("No records\n");
+
+    for (from, to, args) in [
+        ("09:00", "10:30", vec!["--project", "website", "--tag", "css", "fix header"]),
+        ("11:00", "11:30", vec!["emails"]),
+        ("13:00", "14:00", vec!["--project", "blog", "--tag", "css", "header post"]),
+    ] {
+        let mut all = vec!["add", "--date", "2026-10-15", "--from", from, "--to", to];
+        all.extend(args);
+        track(&db, &lockfile, &all).success();
+    }
+
+    let output = log(&[]).success().get_output().stdout.clone();
+    let table = String::from_utf8(output)?;
+    let lines = table.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 4);
+    assert!(lines[0].starts_with("ID"));
+    assert!(lines[1].contains("2026-10-15  09:00  10:30  01:30:00  website"));
+    assert!(lines[1].ends_with("fix header"));
+
+    log(&["--tag", "css", "--search", "HEADER", "--project", "blog"])
+        .success()
+        .stdout(predicates::str::contains("header post").and(predicates::str::contains("fix header").not()));
+
+    let output = log(&["--sort", "duration", "--reverse", "--limit", "1", "--format", "csv"])
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let csv = String::from_utf8(output)?;
+    assert_eq!(csv.lines().count(), 2);
+    assert!(csv.lines().nth(1).unwrap().contains(",5400,website,fix header,css,"));
+
+    log(&["--sort", "length"]).failure();
+
+    Ok(())
+}
+
+#[test]
+fn recover_stops_or_discards_a_stale_session() -> TestResult {
+    let (_temp, db, lockfile) = tracking_paths();
+    let stale = r#"{"start_time":"2026-10-15T09:00:00Z","hostname":"elsewhere","pid":1}"#;
+
+    track(&db, &lockfile, &["recover", "--discard"]).failure();
+
+    // A session copied from another machine is reported as stale
+    std::fs::write(&lockfile, stale)?;
+    track(&db, &lockfile, &["status"])
+        .code(0)
+        .stderr(predicates::str::contains("warning: the session was started on elsewhere"))
+        .stderr(predicates::str::contains("track recover"));
+
+    track(&db, &lockfile, &["recover"]).failure();
+    track(&db, &lockfile, &["recover", "--at", "2026-10-15 17:00", "--discard"]).failure();
+    track(&db, &lockfile, &["recover", "--at", "2026-10-15 17:00"])
+        .success()
+        .stdout(predicates::str::is_match("^Record [0-9a-f]{8} added\n$")?);
+    assert!(!lockfile.exists());
+    track(&db, &lockfile, &["report", "--from", "2026-10-15", "--to", "2026-10-15"])
+        .success()
+        .stdout("08:00:00\n");
+
+    std::fs::write(&lockfile, stale)?;
+    track(&db, &lockfile, &["recover", "--discard"])
+        .success()
+        .stdout("Session discarded\n");
+    assert!(!lockfile.exists());
+
+    Ok(())
+}
+
+/// Runs `track` with each set of arguments at the same time, and returns the
+/// outputs.
+fn track_concurrently(db: &ChildPath, lockfile: &ChildPath, runs: Vec<Vec<String>>) -> Vec<std::process::Output> {
+    let children = runs
+        .into_iter()
+        .map(|args| {
+            std::process::Command::new(assert_cmd::cargo::cargo_bin("track"))
+                .arg("--db-dir")
+                .arg(db.to_path_buf())
+                .arg("--lockfile")
+                .arg(lockfile.to_path_buf())
+                .args(["--tz", "UTC"])
+                .args(args)
+                .stdout(std::process::Stdio::piped())
+                .stderr(std::process::Stdio::piped())
+                .spawn()
+                .unwrap()
+        })
+        .collect::<Vec<_>>();
+
+    children
+        .into_iter()
+        .map(|child| child.wait_with_output().unwrap())
+        .collect()
+}
+
+#[test]
+fn concurrent_commands_lose_no_records() -> TestResult {
+    const RUNS: usize = 24;
+    let (_temp, db, lockfile) = tracking_paths();
+
+    // Every concurrently added record is kept
+    let adds = (0..RUNS)
+        .map(|i| {
+            let from = format!("2026-10-15 {:02}:00", i % 24);
+            let to = format!("2026-10-15 {:02}:30", i % 24);
+            ["add", "--from", &from, "--to", &to].map(String::from).to_vec()
+        })
+        .collect();
+    let outputs = track_concurrently(&db, &lockfile, adds);
+    assert!(outputs.iter().all(|output| output.status.success()));
+
+    let records: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&db)?)?;
+    assert_eq!(records["records"].as_array().unwrap().len(), RUNS);
+    track(&db, &lockfile, &["history"])
+        .success()
+        // Padded, since a short record ID can contain "add" as well
+        .stdout(predicates::str::contains("  add  ").count(RUNS));
+
+    // Only one of many concurrent starts starts the tracker
+    let starts = vec![vec!["start".to_string()]; RUNS];
+    let outputs = track_concurrently(&db, &lockfile, starts);
+    let started = outputs
+        .iter()
+        .filter(|output| output.stdout == b"Tracker started\n")
+        .count();
+    assert_eq!(started, 1);
+
+    // And only one of many concurrent stops records the session
+    let stops = vec![vec!["stop".to_string()]; RUNS];
+    let outputs = track_concurrently(&db, &lockfile, stops);
+    assert_eq!(outputs.iter().filter(|output| output.status.success()).count(), 1);
+
+    let records: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&db)?)?;
+    assert_eq!(records["records"].as_array().unwrap().len(), RUNS + 1);
+
+    Ok(())
+}
+
+#[test]
+fn db_migrate_upgrades_old_database_after_dry_run() -> TestResult {
+    let (temp, db, lockfile) = tracking_paths();
+    let fixture = std::fs::read_to_string("tests/fixtures/flatfile/v0-task.json")?;
+    std::fs::write(&db, &fixture)?;
+
+    // A dry run only tells what would be done
+    track(&db, &lockfile, &["db", "migrate", "--dry-run"])
+        .success()
+        .stdout(predicates::str::contains("Would migrate database from format version 0 to 1"));
+    assert_eq!(std::fs::read_to_string(&db)?, fixture);
+
+    // Migrating keeps the old database as a backup
+    track(&db, &lockfile, &["db", "migrate"])
+        .success()
+        .stdout(predicates::str::contains("Migrated database from format version 0 to 1"));
+    let migrated: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&db)?)?;
+    assert_eq!(migrated["version"], 1);
+    assert_eq!(std::fs::read_to_string(temp.child("db.json.v0.bak"))?, fixture);
+
+    track(&db, &lockfile, &["db", "migrate"])
+        .success()
+        .stdout(predicates::str::contains("Database is up to date"));
+    track(&db, &lockfile, &["report", "--from", "2024-11-23"])
+        .success()
+        .stdout(predicates::str::contains("02:30:00"));
+
+    Ok(())
+}
+
+#[test]
+fn db_convert_copies_records_and_session_between_backends() -> TestResult {
+    let (temp, db, lockfile) = tracking_paths();
+    add_record(&db, &lockfile, "09:00", "10:00").success();
+    add_record(&db, &lockfile, "11:00", "11:30").success();
+    start_tracking(&db, &lockfile)?;
+    let sqlite = format!("sqlite:{}", temp.child("db.sqlite").display());
+
+    track(&db, &lockfile, &["db", "convert", "--from", &format!("flatfile:{}", db.display()), "--to", &sqlite])
+        .success()
+        .stdout(predicates::str::contains("Copied 2 records (01:30:00 tracked)"))
+        .stdout(predicates::str::contains("Copied the running session"));
+
+    // The target is not overwritten by accident
+    track(&db, &lockfile, &["db", "convert", "--from", &format!("flatfile:{}", db.display()), "--to", &sqlite])
+        .failure()
+        .stderr(predicates::str::contains("--force"));
+
+    // And the copy holds the records and the session
+    let jsonl = temp.child("db.jsonl");
+    let other_lockfile = temp.child("other.lock");
+    let args = ["db", "convert", "--from", &sqlite, "--to", &format!("jsonl:{}", jsonl.display())];
+    track(&db, &other_lockfile, &args).success();
+    assert_eq!(std::fs::read_to_string(&jsonl)?.lines().count(), 2);
+    assert!(other_lockfile.exists());
+
+    track(&db, &lockfile, &["db", "convert", "--from", "mysql:db", "--to", &sqlite])
+        .failure()
+        .stderr(predicates::str::contains("unknown backend 'mysql'"));
+
+    Ok(())
+}
+
+#[test]
+fn db_convert_refuses_to_convert_a_database_into_itself() -> TestResult {
+    let (temp, _, lockfile) = tracking_paths();
+    let db = temp.child("db.sqlite");
+    track(&db, &lockfile, &["--backend", "sqlite", "add", "--from", "09:00", "--to", "10:00"]).success();
+
+    let from = format!("sqlite:{}", db.display());
+    let to = format!("sqlite:{}", temp.path().join(".").join("db.sqlite").display());
+    track(&db, &lockfile, &["db", "convert", "--from", &from, "--to", &to, "--force"])
+        .failure()
+        .stderr(predicates::str::contains("are the same database"));
+
+    // The record is still there
+    let output = track(&db, &lockfile, &["--backend", "sqlite", "--format", "csv", "log", "--today"])
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    assert_eq!(String::from_utf8(output)?.lines().count(), 2);
+
+    Ok(())
+}
+
+#[test]
+fn backend_is_chosen_by_flag_env_or_config_file() -> TestResult {
+    let (temp, _, lockfile) = tracking_paths();
+    let db = temp.child("db.sqlite");
+
+    // Every command works against the chosen backend
+    track(&db, &lockfile, &["--backend", "sqlite", "add", "--from", "09:00", "--to", "10:00"]).success();
+    track(&db, &lockfile, &["--backend", "sqlite", "start", "--project", "website"]).success();
+    Command::cargo_bin("track")?
+        .env("TRACK_BACKEND", "sqlite")
+        .args(["--db-dir", &db.display().to_string(), "--lockfile", &lockfile.display().to_string()])
+        .args(["--format", "json", "status"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(r#""project": "website""#));
+    assert!(!lockfile.exists());
+
+    let config = temp.child("config.toml");
+    std::fs::write(&config, "backend = \"sqlite\"\n")?;
+    track(&db, &lockfile, &["--config", &config.display().to_string(), "stop"]).success();
+    track(&db, &lockfile, &["--config", &config.display().to_string(), "log", "--today", "--no-pager"])
+        .success()
+        .stdout(predicates::str::contains("website"));
+
+    // The flag takes precedence over the config file
+    let jsonl = temp.child("db.jsonl");
+    let config = config.display().to_string();
+    track(&jsonl, &lockfile, &["--config", &config, "--backend", "jsonl", "add", "--from", "09:00", "--to", "10:00"])
+        .success();
+    assert_eq!(std::fs::read_to_string(&jsonl)?.lines().count(), 1);
+
+    Ok(())
+}
+
+#[test]
+fn memory_backend_stores_nothing() -> TestResult {
+    let (_temp, db, lockfile) = tracking_paths();
+
+    track(&db, &lockfile, &["--backend", "memory", "start"]).success();
+    track(&db, &lockfile, &["--backend", "memory", "status"]).code(2);
+    assert!(!db.exists());
+    assert!(!lockfile.exists());
+
+    Ok(())
+}
+
+#[test]
+fn unknown_backend_is_rejected_with_suggestion() -> TestResult {
+    let (temp, db, lockfile) = tracking_paths();
+
+    track(&db, &lockfile, &["--backend", "mysql", "status"])
+        .failure()
+        .stderr(predicates::str::contains("unknown backend 'mysql'"))
+        .stderr(predicates::str::contains("--backend, TRACK_BACKEND or `backend` in the config file"));
+
+    let config = temp.child("config.toml");
+    std::fs::write(&config, "backend = \"postgres\"\n")?;
+    track(&db, &lockfile, &["--config", &config.display().to_string(), "status"])
+        .failure()
+        .stderr(predicates::str::contains("unknown backend 'postgres'"));
+
+    Ok(())
+}